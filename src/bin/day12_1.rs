@@ -1,4 +1,6 @@
-use std::{fs::File, io::{BufReader, BufRead}, collections::{VecDeque, HashSet}};
+use std::{fs::File, io::{BufReader, BufRead}};
+
+use day1::search::bfs;
 
 fn main() {
     let file = File::open("data/day12/input.txt").unwrap();
@@ -20,9 +22,10 @@ fn main() {
 
     let start_pos = start_pos.unwrap();
 
-    let cost = find_path_cost(&grid, start_pos).unwrap();
+    let path = find_path(&grid, start_pos).unwrap();
 
-    println!("min cost: {}", cost);
+    print!("{}", render_path(&grid, &path));
+    println!("min cost: {}", path.len() - 1);
 }
 
 
@@ -41,7 +44,7 @@ struct Grid<T: Clone> {
 impl<T: Clone> Grid<T> {
     fn new(width: usize, height: usize, val: T) -> Self {
 
-        Self { width: width, vec: vec![val; width*height] }
+        Self { width, vec: vec![val; width*height] }
     }
 
     fn height(&self) -> usize {
@@ -104,25 +107,21 @@ fn parse_grid<T: Iterator<Item=String>>(lines_iter: T) -> Grid<u8> {
     g
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Entry {
-    pos: Position,
-    cost: i32,
-}
-
-
-fn get_successors(grid: &Grid<u8>, e: &Entry) -> Vec<Entry> {
+fn get_neighbours(pos: Position) -> Vec<Position> {
     let mut v = Vec::new();
-    if e.pos.x > 0 {
-        v.push(Position{x: e.pos.x-1, y: e.pos.y});
+    if pos.x > 0 {
+        v.push(Position{x: pos.x-1, y: pos.y});
     }
-    v.push(Position{x: e.pos.x+1, y: e.pos.y});
-    if e.pos.y > 0 {
-        v.push(Position{x: e.pos.x, y: e.pos.y-1});
+    v.push(Position{x: pos.x+1, y: pos.y});
+    if pos.y > 0 {
+        v.push(Position{x: pos.x, y: pos.y-1});
     }
-    v.push(Position{x: e.pos.x, y: e.pos.y+1});
+    v.push(Position{x: pos.x, y: pos.y+1});
+    v
+}
 
-    v.into_iter().filter(|p| is_reachable(grid, e.pos, *p)).map(|p| Entry{pos: p, cost: e.cost+1}).collect()
+fn get_successors(grid: &Grid<u8>, pos: Position) -> Vec<Position> {
+    get_neighbours(pos).into_iter().filter(|p| is_reachable(grid, pos, *p)).collect()
 }
 
 fn is_reachable(grid: &Grid<u8>, from: Position, to: Position) -> bool {
@@ -134,32 +133,60 @@ fn is_reachable(grid: &Grid<u8>, from: Position, to: Position) -> bool {
     }
 }
 
-fn find_path_cost(grid: &Grid<u8>, start: Position) -> Option<i32> {
-    let mut open_list = VecDeque::<Entry>::new();
-    let mut closed_set = HashSet::<Position>::new();
-
-    open_list.push_back(Entry{pos: start, cost: 0});
-
-    loop {
-        match open_list.pop_front() {
-            None => return None,
-            Some(candidate) if is_goal(*grid.get_pos(candidate.pos)) => { return Some(candidate.cost); }
-            Some(candidate) => {
-                closed_set.insert(candidate.pos);
-                let successors = get_successors(grid, &candidate);
-                for successor in &successors {
-                    if closed_set.contains(&successor.pos) { continue; }
-                    let existing_elem = open_list.iter().enumerate().find(|(_, elem)| elem.pos == successor.pos);
-                    match existing_elem {
-                        Some((_, elem)) if elem.cost <= successor.cost => { continue; }
-                        Some((i, _)) => { open_list.remove(i); }
-                        None => {}
-                    };
-
-                    let idx = open_list.iter().enumerate().find_map(|(i, elem)| if elem.cost > successor.cost { Some(i) } else { None });
-                    open_list.insert(idx.unwrap_or(open_list.len()), *successor);
-                }
-            }
-        }
+/// Returns the positions visited from start to the goal, inclusive of both ends.
+fn find_path(grid: &Grid<u8>, start: Position) -> Option<Vec<Position>> {
+    bfs([start], |&pos| get_successors(grid, pos), |&pos| is_goal(*grid.get_pos(pos))).goal_path()
+}
+
+fn get_direction_char(from: Position, to: Position) -> u8 {
+    if to.x > from.x { b'>' }
+    else if to.x < from.x { b'<' }
+    else if to.y > from.y { b'v' }
+    else { b'^' }
+}
+
+/// Draws the path like the puzzle does, each step marked with the direction taken from it.
+fn render_path(grid: &Grid<u8>, path: &[Position]) -> String {
+    let mut canvas = Grid::new(grid.width, grid.height(), b'.');
+    for step in path.windows(2) {
+        canvas.set(step[0].x, step[0].y, get_direction_char(step[0], step[1]));
+    }
+    if let Some(last) = path.last() {
+        canvas.set(last.x, last.y, *grid.get_pos(*last));
     }
-}
\ No newline at end of file
+
+    let mut s = String::new();
+    for row in canvas.vec.chunks(canvas.width) {
+        s.push_str(std::str::from_utf8(row).unwrap());
+        s.push('\n');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_path_sample() {
+        let file = File::open("data/day12/sample_input.txt").unwrap();
+        let grid = parse_grid(BufReader::new(file).lines().map(|x| x.unwrap()));
+
+        let path = find_path(&grid, Position{x: 0, y: 0}).unwrap();
+        assert_eq!(path.len() - 1, 31);
+        assert_eq!(path[0], Position{x: 0, y: 0});
+        assert_eq!(path[path.len() - 1], Position{x: 5, y: 2});
+
+        let rendered = render_path(&grid, &path);
+        assert_eq!(rendered.lines().count(), 5);
+        assert_eq!(rendered.matches(['>', 'v', '<', '^']).count(), 31);
+        assert_eq!(rendered.lines().nth(2).unwrap().as_bytes()[5], b'E');
+    }
+
+    #[test]
+    fn test_render_path() {
+        let grid = parse_grid(["SbcdE".to_string()].into_iter());
+        let path = (0..5).map(|x| Position{x, y: 0}).collect::<Vec<_>>();
+        assert_eq!(render_path(&grid, &path), ">>>>E\n");
+    }
+}
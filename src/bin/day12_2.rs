@@ -1,4 +1,6 @@
-use std::{fs::File, io::{BufReader, BufRead}, collections::{VecDeque, HashSet}};
+use std::{fs::File, io::{BufReader, BufRead}};
+
+use day1::search::bfs;
 
 fn main() {
     let file = File::open("data/day12/input.txt").unwrap();
@@ -8,20 +10,24 @@ fn main() {
 
     let grid = parse_grid(lines.into_iter().map(|x| x.unwrap()));
 
-    let mut start_pos: Option<Position> = None;
+    let distances = get_distances_to_goal(&grid);
+
+    let mut best: Option<(i32, Position)> = None;
     for y in 0..grid.height() {
         for x in 0..grid.width {
-            if is_start(*grid.get(x, y)) {
-                start_pos = Some(Position{x, y});
-                break;
+            if get_elevation(*grid.get(x, y)) != 0 { continue; }
+            if let Some(cost) = *distances.get(x, y) {
+                if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                    best = Some((cost, Position{x, y}));
+                }
             }
         }
     }
 
-    let start_pos = start_pos.unwrap();
-
-    let cost = find_path_cost(&grid, start_pos).unwrap();
+    let (cost, start_pos) = best.unwrap();
+    let path = path_from_distances(&grid, &distances, start_pos).unwrap();
 
+    print!("{}", render_path(&grid, &path));
     println!("min cost: {}", cost);
 }
 
@@ -41,7 +47,7 @@ struct Grid<T: Clone> {
 impl<T: Clone> Grid<T> {
     fn new(width: usize, height: usize, val: T) -> Self {
 
-        Self { width: width, vec: vec![val; width*height] }
+        Self { width, vec: vec![val; width*height] }
     }
 
     fn height(&self) -> usize {
@@ -85,12 +91,8 @@ fn get_elevation(c: u8) -> i32 {
     }
 }
 
-fn is_start(c: u8) -> bool {
-    c == b'E'
-}
-
 fn is_goal(c: u8) -> bool {
-    c == b'S' || c == b'a'
+    c == b'E'
 }
 
 fn parse_grid<T: Iterator<Item=String>>(lines_iter: T) -> Grid<u8> {
@@ -104,25 +106,26 @@ fn parse_grid<T: Iterator<Item=String>>(lines_iter: T) -> Grid<u8> {
     g
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Entry {
-    pos: Position,
-    cost: i32,
-}
-
-
-fn get_successors(grid: &Grid<u8>, e: &Entry) -> Vec<Entry> {
+fn get_neighbours(pos: Position) -> Vec<Position> {
     let mut v = Vec::new();
-    if e.pos.x > 0 {
-        v.push(Position{x: e.pos.x-1, y: e.pos.y});
+    if pos.x > 0 {
+        v.push(Position{x: pos.x-1, y: pos.y});
     }
-    v.push(Position{x: e.pos.x+1, y: e.pos.y});
-    if e.pos.y > 0 {
-        v.push(Position{x: e.pos.x, y: e.pos.y-1});
+    v.push(Position{x: pos.x+1, y: pos.y});
+    if pos.y > 0 {
+        v.push(Position{x: pos.x, y: pos.y-1});
     }
-    v.push(Position{x: e.pos.x, y: e.pos.y+1});
+    v.push(Position{x: pos.x, y: pos.y+1});
+    v
+}
 
-    v.into_iter().filter(|p| is_reachable(grid, e.pos, *p)).map(|p| Entry{pos: p, cost: e.cost+1}).collect()
+fn get_successors(grid: &Grid<u8>, pos: Position) -> Vec<Position> {
+    get_neighbours(pos).into_iter().filter(|p| is_reachable(grid, pos, *p)).collect()
+}
+
+/// Cells we could have come from to reach pos, i.e. successors in the reversed graph.
+fn get_predecessors(grid: &Grid<u8>, pos: Position) -> Vec<Position> {
+    get_neighbours(pos).into_iter().filter(|p| grid.is_in_bounds(p.x, p.y) && is_reachable(grid, *p, pos)).collect()
 }
 
 fn is_reachable(grid: &Grid<u8>, from: Position, to: Position) -> bool {
@@ -130,36 +133,80 @@ fn is_reachable(grid: &Grid<u8>, from: Position, to: Position) -> bool {
     let to_val = grid.try_get(to.x, to.y);
     match to_val {
         None => false,
-        Some(v) => get_elevation(*from_val) - 1 <= get_elevation(*v)
-    }
-}
-
-fn find_path_cost(grid: &Grid<u8>, start: Position) -> Option<i32> {
-    let mut open_list = VecDeque::<Entry>::new();
-    let mut closed_set = HashSet::<Position>::new();
-
-    open_list.push_back(Entry{pos: start, cost: 0});
-
-    loop {
-        match open_list.pop_front() {
-            None => return None,
-            Some(candidate) if is_goal(*grid.get_pos(candidate.pos)) => { return Some(candidate.cost); }
-            Some(candidate) => {
-                closed_set.insert(candidate.pos);
-                let successors = get_successors(grid, &candidate);
-                for successor in &successors {
-                    if closed_set.contains(&successor.pos) { continue; }
-                    let existing_elem = open_list.iter().enumerate().find(|(_, elem)| elem.pos == successor.pos);
-                    match existing_elem {
-                        Some((_, elem)) if elem.cost <= successor.cost => { continue; }
-                        Some((i, _)) => { open_list.remove(i); }
-                        None => {}
-                    };
-
-                    let idx = open_list.iter().enumerate().find_map(|(i, elem)| if elem.cost > successor.cost { Some(i) } else { None });
-                    open_list.insert(idx.unwrap_or(open_list.len()), *successor);
-                }
-            }
-        }
+        Some(v) => get_elevation(*from_val) + 1 >= get_elevation(*v)
+    }
+}
+
+/// Runs a single BFS backwards from the goal,
+/// giving the number of steps to the goal from every cell (None if it can't get there).
+fn get_distances_to_goal(grid: &Grid<u8>) -> Grid<Option<i32>> {
+    let goals = (0..grid.height())
+        .flat_map(|y| (0..grid.width).map(move |x| Position{x, y}))
+        .filter(|&pos| is_goal(*grid.get_pos(pos)));
+
+    let result = bfs(goals, |&pos| get_predecessors(grid, pos), |_| false);
+
+    let mut distances = Grid::new(grid.width, grid.height(), None);
+    for (pos, cost) in result.distances {
+        distances.set(pos.x, pos.y, Some(cost as i32));
+    }
+    distances
+}
+
+/// Walks downhill through the distance map to recover a shortest path from start to the goal.
+fn path_from_distances(grid: &Grid<u8>, distances: &Grid<Option<i32>>, start: Position) -> Option<Vec<Position>> {
+    let mut cost = (*distances.get_pos(start))?;
+    let mut path = vec![start];
+    let mut current = start;
+    while cost > 0 {
+        current = get_successors(grid, current).into_iter().find(|p| *distances.get_pos(*p) == Some(cost - 1)).unwrap();
+        path.push(current);
+        cost -= 1;
+    }
+    Some(path)
+}
+
+fn get_direction_char(from: Position, to: Position) -> u8 {
+    if to.x > from.x { b'>' }
+    else if to.x < from.x { b'<' }
+    else if to.y > from.y { b'v' }
+    else { b'^' }
+}
+
+/// Draws the path like the puzzle does, each step marked with the direction taken from it.
+fn render_path(grid: &Grid<u8>, path: &[Position]) -> String {
+    let mut canvas = Grid::new(grid.width, grid.height(), b'.');
+    for step in path.windows(2) {
+        canvas.set(step[0].x, step[0].y, get_direction_char(step[0], step[1]));
     }
-}
\ No newline at end of file
+    if let Some(last) = path.last() {
+        canvas.set(last.x, last.y, *grid.get_pos(*last));
+    }
+
+    let mut s = String::new();
+    for row in canvas.vec.chunks(canvas.width) {
+        s.push_str(std::str::from_utf8(row).unwrap());
+        s.push('\n');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_distances_to_goal_sample() {
+        let file = File::open("data/day12/sample_input.txt").unwrap();
+        let grid = parse_grid(BufReader::new(file).lines().map(|x| x.unwrap()));
+
+        let distances = get_distances_to_goal(&grid);
+        assert_eq!(*distances.get(5, 2), Some(0));
+        assert_eq!(*distances.get(0, 0), Some(31));
+        assert_eq!(*distances.get(0, 4), Some(29));
+
+        let path = path_from_distances(&grid, &distances, Position{x: 0, y: 4}).unwrap();
+        assert_eq!(path.len() - 1, 29);
+        assert_eq!(path[path.len() - 1], Position{x: 5, y: 2});
+    }
+}
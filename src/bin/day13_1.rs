@@ -1,115 +1,619 @@
-use std::{fs::File, io::{BufReader, BufRead}, iter::Peekable};
+use std::{fs::File, io::{BufReader, BufRead}, cmp::Ordering, fmt};
 
 fn main() {
+    let explain = std::env::args().any(|a| a == "--explain");
+
     let file = File::open("data/day13/input.txt").unwrap();
     let reader = BufReader::new(file);
-    let mut lines = reader.lines();
+    let lines = reader.lines().map(|l| l.unwrap()).collect::<Vec<_>>();
 
     let mut sum = 0;
 
-    let mut current_index = 1;
+    for (i, pair) in lines.chunks(3).enumerate() {
+        let current_index = i + 1;
+        let first_line_number = (i * 3) + 1;
 
-    loop {
-        let first = parse_packet(&mut lines.next().unwrap().unwrap().chars().peekable());
-        let second = parse_packet(&mut lines.next().unwrap().unwrap().chars().peekable());
+        let first = parse_line(&pair[0]);
+        let second = match pair.get(1) {
+            Some(line) => parse_line(line),
+            None => Err(ParseError::new(0, "missing the second packet of the pair")),
+        };
 
-        println!("first: {:?}", first);
-        println!("second: {:?}", second);
+        let (first, second) = match (first, second) {
+            (Ok(first), Ok(second)) => (first, second),
+            (first, second) => {
+                for (line_number, result) in [(first_line_number, first), (first_line_number + 1, second)] {
+                    if let Err(e) = result {
+                        println!("pair {}: bad packet on line {} at {}", current_index, line_number, e);
+                    }
+                }
+                println!();
+                continue;
+            }
+        };
 
-        let in_order = are_lists_in_order(&first, &second);
-        println!("are in order?: {:?}", in_order);
-        if in_order == Some(true) {
-            sum += current_index;
+        let in_order = if explain {
+            println!("== Pair {} ==", current_index);
+            let (in_order, trace) = explain_in_order(&first, &second);
+            print!("{}", trace);
+            in_order
         }
+        else {
+            println!("first: {}", first);
+            println!("second: {}", second);
 
-        if lines.next().is_none() {
-            break;
+            let in_order = are_in_order(&first, &second);
+            println!("are in order?: {:?}", in_order);
+            in_order
+        };
+        if in_order == Some(true) {
+            sum += current_index;
         }
 
         println!();
-        current_index += 1;
     }
 
     println!("sum: {}", sum);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum PacketItem {
     Num(i32),
     List(Vec<PacketItem>),
 }
 
+// A number compared against a list acts like a list of just that number.
+fn as_list(item: &PacketItem) -> &[PacketItem] {
+    match item {
+        PacketItem::Num(_) => std::slice::from_ref(item),
+        PacketItem::List(v) => v,
+    }
+}
+
+// Nested lists are walked with an explicit stack rather than recursing,
+// so comparing, printing or dropping arbitrarily deep packets can't blow the call stack.
+impl Ord for PacketItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the rest of each pair of lists still being compared, innermost last
+        let mut stack: Vec<(&[PacketItem], &[PacketItem])> = Vec::new();
+        let mut next = Some((self, other));
+        loop {
+            if let Some((left, right)) = next.take() {
+                match (left, right) {
+                    (PacketItem::Num(i), PacketItem::Num(j)) => {
+                        if i != j {
+                            return i.cmp(j);
+                        }
+                    }
+                    _ => { stack.push((as_list(left), as_list(right))); }
+                }
+            }
+
+            let Some((left, right)) = stack.last_mut() else {
+                return Ordering::Equal;
+            };
+            match (left.split_first(), right.split_first()) {
+                (Some((l, left_rest)), Some((r, right_rest))) => {
+                    *left = left_rest;
+                    *right = right_rest;
+                    next = Some((l, r));
+                }
+                (None, None) => { stack.pop(); }
+                (None, Some(_)) => { return Ordering::Less; }
+                (Some(_), None) => { return Ordering::Greater; }
+            }
+        }
+    }
+}
+
+impl PartialOrd for PacketItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Has to agree with Ord, so e.g. 2 == [2] == [[2]].
+impl PartialEq for PacketItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PacketItem {}
+
+impl fmt::Display for PacketItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the rest of each list still being written, and whether it's still on its first item
+        let mut stack: Vec<(bool, std::slice::Iter<PacketItem>)> = Vec::new();
+        let mut next = Some(self);
+        loop {
+            match next.take() {
+                Some(PacketItem::Num(i)) => { write!(f, "{}", i)?; }
+                Some(PacketItem::List(v)) => {
+                    write!(f, "[")?;
+                    stack.push((true, v.iter()));
+                }
+                None => {}
+            }
+
+            let Some((first, items)) = stack.last_mut() else {
+                return Ok(());
+            };
+            match items.next() {
+                Some(item) => {
+                    if !*first {
+                        write!(f, ",")?;
+                    }
+                    *first = false;
+                    next = Some(item);
+                }
+                None => {
+                    write!(f, "]")?;
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+impl Drop for PacketItem {
+    fn drop(&mut self) {
+        // The default drop recurses once per level, so empty out the nested lists as we go instead.
+        if let PacketItem::List(v) = self {
+            let mut pending = std::mem::take(v);
+            while let Some(mut item) = pending.pop() {
+                if let PacketItem::List(inner) = &mut item {
+                    pending.append(inner);
+                }
+            }
+        }
+    }
+}
+
 fn are_nums_in_order(left: i32, right: i32) -> Option<bool> {
     if left < right { Some(true) }
     else if left > right { Some(false) }
     else { None }
 }
 
-fn are_lists_in_order(left: &[PacketItem], right: &[PacketItem]) -> Option<bool> {
-    if left.is_empty() && right.is_empty() { None }
-    else if left.is_empty() { Some(true) }
-    else if right.is_empty() { Some(false) }
-    else { 
-        match are_in_order(&left[0], &right[0]) {
-            Some(x) => Some(x),
-            None => are_lists_in_order(&left[1..], &right[1..])
+// Some(true) if left should come first, None if the puzzle's rules can't tell them apart.
+fn are_in_order(left: &PacketItem, right: &PacketItem) -> Option<bool> {
+    match left.cmp(right) {
+        Ordering::Less => Some(true),
+        Ordering::Greater => Some(false),
+        Ordering::Equal => None,
+    }
+}
+
+/// Same as are_in_order, but also returns the reasoning
+/// written out like the walkthrough in the puzzle text.
+fn explain_in_order(left: &PacketItem, right: &PacketItem) -> (Option<bool>, String) {
+    let mut out = String::new();
+    let result = explain_items(left, right, &mut out);
+    (result, out)
+}
+
+fn explain_line(out: &mut String, depth: usize, line: &str) {
+    out.push_str(&"  ".repeat(depth));
+    out.push_str("- ");
+    out.push_str(line);
+    out.push('\n');
+}
+
+// A pair of lists being explained, and how far through them we've got.
+struct ExplainFrame<'a> {
+    left: &'a [PacketItem],
+    right: &'a [PacketItem],
+    depth: usize,
+    index: usize,
+}
+
+// Walks the packets with an explicit stack of frames, like cmp, so deeply nested packets can't blow the call stack.
+fn explain_items(left: &PacketItem, right: &PacketItem, out: &mut String) -> Option<bool> {
+    let mut stack: Vec<ExplainFrame> = Vec::new();
+    let mut next = Some((left, right, 0));
+    loop {
+        if let Some((left, right, depth)) = next.take() {
+            explain_line(out, depth, &format!("Compare {} vs {}", left, right));
+
+            match (left, right) {
+                (PacketItem::Num(i), PacketItem::Num(j)) => {
+                    let result = are_nums_in_order(*i, *j);
+                    match result {
+                        Some(true) => explain_line(out, depth + 1, "Left side is smaller, so inputs are in the right order"),
+                        Some(false) => explain_line(out, depth + 1, "Right side is smaller, so inputs are not in the right order"),
+                        None => {}
+                    }
+                    if result.is_some() {
+                        return result;
+                    }
+                }
+                (PacketItem::List(v), PacketItem::List(u)) => stack.push(ExplainFrame { left: v, right: u, depth, index: 0 }),
+                (PacketItem::List(v), PacketItem::Num(j)) => {
+                    let converted = PacketItem::List(vec![PacketItem::Num(*j)]);
+                    explain_line(out, depth + 1, &format!("Mixed types; convert right to {} and retry comparison", converted));
+                    explain_line(out, depth + 1, &format!("Compare {} vs {}", left, converted));
+                    stack.push(ExplainFrame { left: v, right: std::slice::from_ref(right), depth: depth + 1, index: 0 });
+                }
+                (PacketItem::Num(i), PacketItem::List(u)) => {
+                    let converted = PacketItem::List(vec![PacketItem::Num(*i)]);
+                    explain_line(out, depth + 1, &format!("Mixed types; convert left to {} and retry comparison", converted));
+                    explain_line(out, depth + 1, &format!("Compare {} vs {}", converted, right));
+                    stack.push(ExplainFrame { left: std::slice::from_ref(left), right: u, depth: depth + 1, index: 0 });
+                }
+            }
+        }
+
+        // nothing left to compare, so they're the same as far as the rules go
+        let frame = stack.last_mut()?;
+        match (frame.left.get(frame.index), frame.right.get(frame.index)) {
+            (Some(l), Some(r)) => {
+                frame.index += 1;
+                next = Some((l, r, frame.depth + 1));
+            }
+            (None, Some(_)) => {
+                explain_line(out, frame.depth + 1, "Left side ran out of items, so inputs are in the right order");
+                return Some(true);
+            }
+            (Some(_), None) => {
+                explain_line(out, frame.depth + 1, "Right side ran out of items, so inputs are not in the right order");
+                return Some(false);
+            }
+            (None, None) => { stack.pop(); }
         }
     }
 }
 
-fn are_in_order(left: &PacketItem, right: &PacketItem) -> Option<bool> {
-    match (left, right) {
-        (PacketItem::Num(i), PacketItem::Num(j)) => are_nums_in_order(*i, *j),
-        (PacketItem::List(v), PacketItem::List(u)) => are_lists_in_order(v, u),
-        (PacketItem::List(v), PacketItem::Num(j)) => are_lists_in_order(v, &[PacketItem::Num(*j)]),
-        (PacketItem::Num(i), PacketItem::List(u)) => are_lists_in_order(&[PacketItem::Num(*i)], u),
+#[derive(Debug, PartialEq, Eq)]
+struct ParseError {
+    offset: usize,
+    message: String,
+}
+
+impl ParseError {
+    fn new(offset: usize, message: &str) -> Self {
+        Self { offset, message: message.to_string() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "byte {}: {}", self.offset, self.message)
+    }
+}
+
+fn parse_line(line: &str) -> Result<PacketItem, ParseError> {
+    parse_packet(line.as_bytes())
+}
+
+fn skip_whitespace(s: &[u8], mut pos: usize) -> usize {
+    while pos < s.len() && s[pos].is_ascii_whitespace() {
+        pos += 1;
     }
+    pos
 }
 
-fn parse_number<T: Iterator<Item=char>>(s: &mut Peekable<T>) -> i32 {
-    let mut num_str = String::new();
+/// Returns the number and the offset just past it.
+fn parse_number(s: &[u8], start: usize) -> Result<(i32, usize), ParseError> {
+    let mut pos = start;
+    if s.get(pos) == Some(&b'-') {
+        pos += 1;
+    }
+    let digits_start = pos;
+    while pos < s.len() && s[pos].is_ascii_digit() {
+        pos += 1;
+    }
+    if pos == digits_start {
+        return Err(ParseError::new(pos, "expected digit"));
+    }
+
+    // only ascii got consumed so this can't fail
+    let num_str = std::str::from_utf8(&s[start..pos]).unwrap();
+    match num_str.parse() {
+        Ok(n) => Ok((n, pos)),
+        Err(_) => Err(ParseError::new(start, "number out of range")),
+    }
+}
+
+/// Parses a whole packet, which must be a list.
+/// Nested lists are kept on an explicit stack rather than recursing,
+/// so arbitrarily deep input can't blow the call stack.
+fn parse_packet(s: &[u8]) -> Result<PacketItem, ParseError> {
+    let mut pos = skip_whitespace(s, 0);
+    match s.get(pos) {
+        Some(b'[') => { pos += 1; }
+        Some(_) => { return Err(ParseError::new(pos, "expected '['")); }
+        None => { return Err(ParseError::new(pos, "unexpected end of input")); }
+    }
+
+    let mut stack = vec![Vec::<PacketItem>::new()];
+    let mut at_list_start = true;
+
     loop {
-        match s.peek() {
-            Some(x) if x.is_ascii_digit() => { num_str.push(s.next().unwrap()); }
-            _ => { break; }
+        // expecting a value, or the end of the list if it's empty
+        pos = skip_whitespace(s, pos);
+        match s.get(pos) {
+            Some(b']') if at_list_start => {}
+            Some(b'[') => {
+                stack.push(Vec::new());
+                pos += 1;
+                at_list_start = true;
+                continue;
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                let (n, next) = parse_number(s, pos)?;
+                stack.last_mut().unwrap().push(PacketItem::Num(n));
+                pos = next;
+            }
+            Some(_) => { return Err(ParseError::new(pos, "expected '[' or number")); }
+            None => { return Err(ParseError::new(pos, "unexpected end of input")); }
+        }
+
+        // finished a value, so the list either continues or closes (maybe several levels at once)
+        loop {
+            pos = skip_whitespace(s, pos);
+            match s.get(pos) {
+                Some(b',') => {
+                    pos += 1;
+                    at_list_start = false;
+                    break;
+                }
+                Some(b']') => {
+                    pos += 1;
+                    let list = PacketItem::List(stack.pop().unwrap());
+                    match stack.last_mut() {
+                        Some(parent) => { parent.push(list); }
+                        None => {
+                            pos = skip_whitespace(s, pos);
+                            if pos != s.len() {
+                                return Err(ParseError::new(pos, "unexpected input after end of packet"));
+                            }
+                            return Ok(list);
+                        }
+                    }
+                }
+                Some(_) => { return Err(ParseError::new(pos, "expected ',' or ']'")); }
+                None => { return Err(ParseError::new(pos, "unexpected end of input")); }
+            }
         }
     }
-    num_str.parse().unwrap()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tiny xorshift so the tests don't need a rand dependency.
+    struct Rng(u64);
 
-fn parse_item<T: Iterator<Item=char>>(s: &mut Peekable<T>) -> PacketItem {
-    match s.peek() {
-        Some('[') => PacketItem::List(parse_packet(s)),
-        Some(_) => PacketItem::Num(parse_number(s)),
-        None => panic!("unexpected end of input"),
+    impl Rng {
+        fn next(&mut self, max: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % max
+        }
     }
-}
 
-fn parse_packet<T: Iterator<Item=char>>(s: &mut Peekable<T>) -> Vec<PacketItem> {
-    let mut v = Vec::new();
-    assert!(s.next().unwrap() == '[');
-    match s.peek() {
-        Some(']') => {
-            s.next();
-            return v;
+    fn random_item(rng: &mut Rng, depth: u32) -> PacketItem {
+        if depth == 0 || rng.next(3) == 0 {
+            PacketItem::Num(rng.next(4) as i32)
+        }
+        else {
+            PacketItem::List((0..rng.next(4)).map(|_| random_item(rng, depth - 1)).collect())
         }
-        Some(_) => { v.push(parse_item(s)); }
-        None => { panic!("unexpected end of input"); }
     }
 
-    loop {
-        match s.peek() {
-            Some(']') => { 
-                s.next();
-                return v;
+    fn random_packet(rng: &mut Rng) -> PacketItem {
+        PacketItem::List((0..rng.next(4)).map(|_| random_item(rng, 3)).collect())
+    }
+
+    fn read_packets(path: &str) -> Vec<String> {
+        let file = File::open(path).unwrap();
+        BufReader::new(file).lines().map(|l| l.unwrap()).filter(|l| !l.is_empty()).collect()
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for line in read_packets("data/day13/sample_input.txt").into_iter().chain(read_packets("data/day13/input.txt")) {
+            assert_eq!(parse_line(&line).unwrap().to_string(), line);
+        }
+
+        let mut rng = Rng(0x2022_1213);
+        for _ in 0..1000 {
+            let packet = random_packet(&mut rng);
+            let s = packet.to_string();
+            assert_eq!(parse_line(&s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_ord_matches_sample() {
+        let packets = read_packets("data/day13/sample_input.txt").iter().map(|l| parse_line(l).unwrap()).collect::<Vec<_>>();
+        let in_order = packets.chunks(2).map(|pair| pair[0] < pair[1]).collect::<Vec<_>>();
+        assert_eq!(in_order, vec![true, true, false, true, false, true, false, false]);
+    }
+
+    #[test]
+    fn test_ord_agrees_with_explain() {
+        let mut rng = Rng(0x1234_5678);
+        let packets = (0..200).map(|_| random_packet(&mut rng)).collect::<Vec<_>>();
+
+        for a in &packets {
+            for b in &packets {
+                // explain_items follows the puzzle's rules directly
+                let expected = match explain_in_order(a, b).0 {
+                    Some(true) => Ordering::Less,
+                    Some(false) => Ordering::Greater,
+                    None => Ordering::Equal,
+                };
+                assert_eq!(a.cmp(b), expected, "{} vs {}", a, b);
             }
-            Some(',') => {
-                s.next();
-                v.push(parse_item(s));
+        }
+
+        let mut sorted = packets.clone();
+        sorted.sort();
+        for i in 0..sorted.len() {
+            for j in i+1..sorted.len() {
+                assert_ne!(explain_in_order(&sorted[i], &sorted[j]).0, Some(false), "{} vs {}", sorted[i], sorted[j]);
             }
-            Some(_) => { panic!("unexpected input"); }
-            None => { panic!("unexpected end of input"); }
         }
     }
+
+    #[test]
+    fn test_parse_packet_whitespace_and_negatives() {
+        let packet = parse_line(" [ 1 , [-2,3],\t[ ] ] ").unwrap();
+        assert_eq!(packet.to_string(), "[1,[-2,3],[]]");
+    }
+
+    #[test]
+    fn test_parse_packet_errors() {
+        assert_eq!(parse_line(""), Err(ParseError::new(0, "unexpected end of input")));
+        assert_eq!(parse_line("1"), Err(ParseError::new(0, "expected '['")));
+        assert_eq!(parse_line("[1,]"), Err(ParseError::new(3, "expected '[' or number")));
+        assert_eq!(parse_line("[1 2]"), Err(ParseError::new(3, "expected ',' or ']'")));
+        assert_eq!(parse_line("[[1]"), Err(ParseError::new(4, "unexpected end of input")));
+        assert_eq!(parse_line("[1]]"), Err(ParseError::new(3, "unexpected input after end of packet")));
+        assert_eq!(parse_line("[-]"), Err(ParseError::new(2, "expected digit")));
+        assert_eq!(parse_line("[99999999999]"), Err(ParseError::new(1, "number out of range")));
+    }
+
+    #[test]
+    fn test_parse_packet_deep_nesting() {
+        let depth = 10000;
+        let s = "[".repeat(depth) + "7" + &"]".repeat(depth);
+
+        let mut item = &parse_line(&s).unwrap();
+        let mut levels = 0;
+        while let PacketItem::List(v) = item {
+            assert_eq!(v.len(), 1);
+            item = &v[0];
+            levels += 1;
+        }
+        assert_eq!(levels, depth);
+        assert!(matches!(item, PacketItem::Num(7)));
+    }
+
+    #[test]
+    fn test_very_deep_packets() {
+        let depth = 1000000;
+        let left = parse_line(&("[".repeat(depth) + "7" + &"]".repeat(depth))).unwrap();
+        let right = parse_line(&("[".repeat(depth) + "8" + &"]".repeat(depth))).unwrap();
+
+        assert_eq!(are_in_order(&left, &right), Some(true));
+        assert_eq!(right.cmp(&left), Ordering::Greater);
+        assert_eq!(left.cmp(&left), Ordering::Equal);
+        // a number against the same number buried in lists
+        assert_eq!(PacketItem::Num(7).cmp(&left), Ordering::Equal);
+
+        let s = left.to_string();
+        assert_eq!(s.len(), (2 * depth) + 1);
+        assert!(s.starts_with("[[[") && s.contains("[7]") && s.ends_with("]]]"));
+
+        drop(left);
+        drop(right);
+    }
+
+    #[test]
+    fn test_explain_very_deep_packets() {
+        // The trace repeats both packets at every level, so it grows with the square of the depth.
+        // A small stack keeps the depth low enough for that while still being too deep to recurse through.
+        let depth = 2000;
+        let left = parse_line(&("[".repeat(depth) + "7" + &"]".repeat(depth))).unwrap();
+        let right = parse_line(&("[".repeat(depth - 1) + "[8,1]" + &"]".repeat(depth - 1))).unwrap();
+
+        let (in_order, trace) = std::thread::Builder::new().stack_size(64 * 1024)
+            .spawn(move || explain_in_order(&left, &right))
+            .unwrap().join().unwrap();
+
+        assert_eq!(in_order, Some(true));
+        let lines = trace.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), depth + 2);
+        assert!(lines[0].starts_with("- Compare [[[") && lines[0].ends_with("]]]"));
+        assert_eq!(lines[depth].trim_start(), "- Compare 7 vs 8");
+        assert_eq!(lines[depth + 1], format!("{}- Left side is smaller, so inputs are in the right order", "  ".repeat(depth + 1)));
+    }
+
+    #[test]
+    fn test_explain_in_order_sample() {
+        let packets = read_packets("data/day13/sample_input.txt").iter().map(|l| parse_line(l).unwrap()).collect::<Vec<_>>();
+
+        let mut trace = String::new();
+        for (i, pair) in packets.chunks(2).enumerate() {
+            let (in_order, pair_trace) = explain_in_order(&pair[0], &pair[1]);
+            assert_eq!(in_order, are_in_order(&pair[0], &pair[1]));
+            trace.push_str(&format!("== Pair {} ==\n", i + 1));
+            trace.push_str(&pair_trace);
+            trace.push('\n');
+        }
+
+        let expected = "\
+== Pair 1 ==
+- Compare [1,1,3,1,1] vs [1,1,5,1,1]
+  - Compare 1 vs 1
+  - Compare 1 vs 1
+  - Compare 3 vs 5
+    - Left side is smaller, so inputs are in the right order
+
+== Pair 2 ==
+- Compare [[1],[2,3,4]] vs [[1],4]
+  - Compare [1] vs [1]
+    - Compare 1 vs 1
+  - Compare [2,3,4] vs 4
+    - Mixed types; convert right to [4] and retry comparison
+    - Compare [2,3,4] vs [4]
+      - Compare 2 vs 4
+        - Left side is smaller, so inputs are in the right order
+
+== Pair 3 ==
+- Compare [9] vs [[8,7,6]]
+  - Compare 9 vs [8,7,6]
+    - Mixed types; convert left to [9] and retry comparison
+    - Compare [9] vs [8,7,6]
+      - Compare 9 vs 8
+        - Right side is smaller, so inputs are not in the right order
+
+== Pair 4 ==
+- Compare [[4,4],4,4] vs [[4,4],4,4,4]
+  - Compare [4,4] vs [4,4]
+    - Compare 4 vs 4
+    - Compare 4 vs 4
+  - Compare 4 vs 4
+  - Compare 4 vs 4
+  - Left side ran out of items, so inputs are in the right order
+
+== Pair 5 ==
+- Compare [7,7,7,7] vs [7,7,7]
+  - Compare 7 vs 7
+  - Compare 7 vs 7
+  - Compare 7 vs 7
+  - Right side ran out of items, so inputs are not in the right order
+
+== Pair 6 ==
+- Compare [] vs [3]
+  - Left side ran out of items, so inputs are in the right order
+
+== Pair 7 ==
+- Compare [[[]]] vs [[]]
+  - Compare [[]] vs []
+    - Right side ran out of items, so inputs are not in the right order
+
+== Pair 8 ==
+- Compare [1,[2,[3,[4,[5,6,7]]]],8,9] vs [1,[2,[3,[4,[5,6,0]]]],8,9]
+  - Compare 1 vs 1
+  - Compare [2,[3,[4,[5,6,7]]]] vs [2,[3,[4,[5,6,0]]]]
+    - Compare 2 vs 2
+    - Compare [3,[4,[5,6,7]]] vs [3,[4,[5,6,0]]]
+      - Compare 3 vs 3
+      - Compare [4,[5,6,7]] vs [4,[5,6,0]]
+        - Compare 4 vs 4
+        - Compare [5,6,7] vs [5,6,0]
+          - Compare 5 vs 5
+          - Compare 6 vs 6
+          - Compare 7 vs 0
+            - Right side is smaller, so inputs are not in the right order
+
+";
+        assert_eq!(trace, expected);
+    }
 }
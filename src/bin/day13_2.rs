@@ -1,112 +1,296 @@
-use std::{fs::File, io::{BufReader, BufRead}, iter::Peekable, cmp::Ordering};
+use std::{fs::File, io::{BufReader, BufRead}, cmp::Ordering, fmt};
 
 fn main() {
     let file = File::open("data/day13/input.txt").unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines();
 
-    let mut packets = lines.map(|l| l.unwrap()).filter(|l| l != "").map(|l| parse_packet(&mut l.chars().peekable())).collect::<Vec<_>>();
-    let divider_packet_2 = parse_packet(&mut "[[2]]".chars().peekable());
-    let divider_packet_6 = parse_packet(&mut "[[6]]".chars().peekable());
+    let mut packets = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line.unwrap();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_line(&line) {
+            Ok(packet) => { packets.push(packet); }
+            Err(e) => { println!("skipping bad packet on line {} at {}", i + 1, e); }
+        }
+    }
+
+    println!("decoder key: {}", get_decoder_key(&mut packets));
+}
+
+fn get_decoder_key(packets: &mut Vec<PacketItem>) -> usize {
+    let divider_packet_2 = parse_line("[[2]]").unwrap();
+    let divider_packet_6 = parse_line("[[6]]").unwrap();
     packets.push(divider_packet_2.clone());
     packets.push(divider_packet_6.clone());
 
-    packets.sort_by(|a, b| cmp_lists(a, b));
-
+    packets.sort();
 
-    let divider_packet_2_index = packets.binary_search_by(|probe| cmp_lists(probe, &divider_packet_2)).unwrap() + 1;
+    let divider_packet_2_index = packets.binary_search(&divider_packet_2).unwrap() + 1;
 
     // We could try and make use of how we know that packet 2 will be before packet 6, but, _eh_.
-    let divider_packet_6_index = packets.binary_search_by(|probe| cmp_lists(probe, &divider_packet_6)).unwrap() + 1;
+    let divider_packet_6_index = packets.binary_search(&divider_packet_6).unwrap() + 1;
 
-    println!("decoder key: {}", divider_packet_2_index * divider_packet_6_index);
+    divider_packet_2_index * divider_packet_6_index
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 enum PacketItem {
     Num(i32),
     List(Vec<PacketItem>),
 }
 
-fn cmp_lists(left: &[PacketItem], right: &[PacketItem]) -> Ordering {
-    match are_lists_in_order(left, right) {
-        Some(true) => std::cmp::Ordering::Less,
-        Some(false) => std::cmp::Ordering::Greater,
-        None => std::cmp::Ordering::Equal
+// A number compared against a list acts like a list of just that number.
+fn as_list(item: &PacketItem) -> &[PacketItem] {
+    match item {
+        PacketItem::Num(_) => std::slice::from_ref(item),
+        PacketItem::List(v) => v,
     }
 }
 
-fn are_nums_in_order(left: i32, right: i32) -> Option<bool> {
-    if left < right { Some(true) }
-    else if left > right { Some(false) }
-    else { None }
-}
+// Nested lists are walked with an explicit stack rather than recursing,
+// so comparing, printing or dropping arbitrarily deep packets can't blow the call stack.
+impl Ord for PacketItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the rest of each pair of lists still being compared, innermost last
+        let mut stack: Vec<(&[PacketItem], &[PacketItem])> = Vec::new();
+        let mut next = Some((self, other));
+        loop {
+            if let Some((left, right)) = next.take() {
+                match (left, right) {
+                    (PacketItem::Num(i), PacketItem::Num(j)) => {
+                        if i != j {
+                            return i.cmp(j);
+                        }
+                    }
+                    _ => { stack.push((as_list(left), as_list(right))); }
+                }
+            }
 
-fn are_lists_in_order(left: &[PacketItem], right: &[PacketItem]) -> Option<bool> {
-    if left.is_empty() && right.is_empty() { None }
-    else if left.is_empty() { Some(true) }
-    else if right.is_empty() { Some(false) }
-    else { 
-        match are_in_order(&left[0], &right[0]) {
-            Some(x) => Some(x),
-            None => are_lists_in_order(&left[1..], &right[1..])
+            let Some((left, right)) = stack.last_mut() else {
+                return Ordering::Equal;
+            };
+            match (left.split_first(), right.split_first()) {
+                (Some((l, left_rest)), Some((r, right_rest))) => {
+                    *left = left_rest;
+                    *right = right_rest;
+                    next = Some((l, r));
+                }
+                (None, None) => { stack.pop(); }
+                (None, Some(_)) => { return Ordering::Less; }
+                (Some(_), None) => { return Ordering::Greater; }
+            }
         }
     }
 }
 
-fn are_in_order(left: &PacketItem, right: &PacketItem) -> Option<bool> {
-    match (left, right) {
-        (PacketItem::Num(i), PacketItem::Num(j)) => are_nums_in_order(*i, *j),
-        (PacketItem::List(v), PacketItem::List(u)) => are_lists_in_order(v, u),
-        (PacketItem::List(v), PacketItem::Num(j)) => are_lists_in_order(v, &[PacketItem::Num(*j)]),
-        (PacketItem::Num(i), PacketItem::List(u)) => are_lists_in_order(&[PacketItem::Num(*i)], u),
+impl PartialOrd for PacketItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-fn parse_number<T: Iterator<Item=char>>(s: &mut Peekable<T>) -> i32 {
-    let mut num_str = String::new();
-    loop {
-        match s.peek() {
-            Some(x) if x.is_ascii_digit() => { num_str.push(s.next().unwrap()); }
-            _ => { break; }
-        }
+// Has to agree with Ord, so e.g. 2 == [2] == [[2]].
+impl PartialEq for PacketItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
     }
-    num_str.parse().unwrap()
 }
 
+impl Eq for PacketItem {}
+
+impl fmt::Display for PacketItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the rest of each list still being written, and whether it's still on its first item
+        let mut stack: Vec<(bool, std::slice::Iter<PacketItem>)> = Vec::new();
+        let mut next = Some(self);
+        loop {
+            match next.take() {
+                Some(PacketItem::Num(i)) => { write!(f, "{}", i)?; }
+                Some(PacketItem::List(v)) => {
+                    write!(f, "[")?;
+                    stack.push((true, v.iter()));
+                }
+                None => {}
+            }
 
-fn parse_item<T: Iterator<Item=char>>(s: &mut Peekable<T>) -> PacketItem {
-    match s.peek() {
-        Some('[') => PacketItem::List(parse_packet(s)),
-        Some(_) => PacketItem::Num(parse_number(s)),
-        None => panic!("unexpected end of input"),
+            let Some((first, items)) = stack.last_mut() else {
+                return Ok(());
+            };
+            match items.next() {
+                Some(item) => {
+                    if !*first {
+                        write!(f, ",")?;
+                    }
+                    *first = false;
+                    next = Some(item);
+                }
+                None => {
+                    write!(f, "]")?;
+                    stack.pop();
+                }
+            }
+        }
     }
 }
 
-fn parse_packet<T: Iterator<Item=char>>(s: &mut Peekable<T>) -> Vec<PacketItem> {
-    let mut v = Vec::new();
-    assert!(s.next().unwrap() == '[');
-    match s.peek() {
-        Some(']') => {
-            s.next();
-            return v;
+impl Drop for PacketItem {
+    fn drop(&mut self) {
+        // The default drop recurses once per level, so empty out the nested lists as we go instead.
+        if let PacketItem::List(v) = self {
+            let mut pending = std::mem::take(v);
+            while let Some(mut item) = pending.pop() {
+                if let PacketItem::List(inner) = &mut item {
+                    pending.append(inner);
+                }
+            }
         }
-        Some(_) => { v.push(parse_item(s)); }
-        None => { panic!("unexpected end of input"); }
     }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ParseError {
+    offset: usize,
+    message: String,
+}
+
+impl ParseError {
+    fn new(offset: usize, message: &str) -> Self {
+        Self { offset, message: message.to_string() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "byte {}: {}", self.offset, self.message)
+    }
+}
+
+fn parse_line(line: &str) -> Result<PacketItem, ParseError> {
+    parse_packet(line.as_bytes())
+}
+
+fn skip_whitespace(s: &[u8], mut pos: usize) -> usize {
+    while pos < s.len() && s[pos].is_ascii_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+/// Returns the number and the offset just past it.
+fn parse_number(s: &[u8], start: usize) -> Result<(i32, usize), ParseError> {
+    let mut pos = start;
+    if s.get(pos) == Some(&b'-') {
+        pos += 1;
+    }
+    let digits_start = pos;
+    while pos < s.len() && s[pos].is_ascii_digit() {
+        pos += 1;
+    }
+    if pos == digits_start {
+        return Err(ParseError::new(pos, "expected digit"));
+    }
+
+    // only ascii got consumed so this can't fail
+    let num_str = std::str::from_utf8(&s[start..pos]).unwrap();
+    match num_str.parse() {
+        Ok(n) => Ok((n, pos)),
+        Err(_) => Err(ParseError::new(start, "number out of range")),
+    }
+}
+
+/// Parses a whole packet, which must be a list.
+/// Nested lists are kept on an explicit stack rather than recursing,
+/// so arbitrarily deep input can't blow the call stack.
+fn parse_packet(s: &[u8]) -> Result<PacketItem, ParseError> {
+    let mut pos = skip_whitespace(s, 0);
+    match s.get(pos) {
+        Some(b'[') => { pos += 1; }
+        Some(_) => { return Err(ParseError::new(pos, "expected '['")); }
+        None => { return Err(ParseError::new(pos, "unexpected end of input")); }
+    }
+
+    let mut stack = vec![Vec::<PacketItem>::new()];
+    let mut at_list_start = true;
 
     loop {
-        match s.peek() {
-            Some(']') => { 
-                s.next();
-                return v;
+        // expecting a value, or the end of the list if it's empty
+        pos = skip_whitespace(s, pos);
+        match s.get(pos) {
+            Some(b']') if at_list_start => {}
+            Some(b'[') => {
+                stack.push(Vec::new());
+                pos += 1;
+                at_list_start = true;
+                continue;
             }
-            Some(',') => {
-                s.next();
-                v.push(parse_item(s));
+            Some(b'-' | b'0'..=b'9') => {
+                let (n, next) = parse_number(s, pos)?;
+                stack.last_mut().unwrap().push(PacketItem::Num(n));
+                pos = next;
             }
-            Some(_) => { panic!("unexpected input"); }
-            None => { panic!("unexpected end of input"); }
+            Some(_) => { return Err(ParseError::new(pos, "expected '[' or number")); }
+            None => { return Err(ParseError::new(pos, "unexpected end of input")); }
         }
+
+        // finished a value, so the list either continues or closes (maybe several levels at once)
+        loop {
+            pos = skip_whitespace(s, pos);
+            match s.get(pos) {
+                Some(b',') => {
+                    pos += 1;
+                    at_list_start = false;
+                    break;
+                }
+                Some(b']') => {
+                    pos += 1;
+                    let list = PacketItem::List(stack.pop().unwrap());
+                    match stack.last_mut() {
+                        Some(parent) => { parent.push(list); }
+                        None => {
+                            pos = skip_whitespace(s, pos);
+                            if pos != s.len() {
+                                return Err(ParseError::new(pos, "unexpected input after end of packet"));
+                            }
+                            return Ok(list);
+                        }
+                    }
+                }
+                Some(_) => { return Err(ParseError::new(pos, "expected ',' or ']'")); }
+                None => { return Err(ParseError::new(pos, "unexpected end of input")); }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_decoder_key_sample() {
+        let file = File::open("data/day13/sample_input.txt").unwrap();
+        let mut packets = BufReader::new(file).lines().map(|l| l.unwrap()).filter(|l| !l.is_empty()).map(|l| parse_line(&l).unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(get_decoder_key(&mut packets), 140);
+        assert_eq!(packets[0].to_string(), "[]");
+        assert_eq!(packets[1].to_string(), "[[]]");
+        assert_eq!(packets[2].to_string(), "[[[]]]");
+        assert_eq!(packets[packets.len() - 1].to_string(), "[9]");
+    }
+
+    #[test]
+    fn test_very_deep_packets() {
+        let depth = 1000000;
+        let deep = parse_line(&("[".repeat(depth) + "3" + &"]".repeat(depth))).unwrap();
+        let mut packets = vec![deep];
+
+        // the very deep [3] sorts between the two divider packets
+        assert_eq!(get_decoder_key(&mut packets), 3);
+        assert_eq!(packets[0].to_string(), "[[2]]");
+        assert_eq!(packets[1].to_string().len(), (2 * depth) + 1);
+        assert_eq!(packets[2].to_string(), "[[6]]");
     }
 }
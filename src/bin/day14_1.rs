@@ -1,79 +1,329 @@
-use std::{fs::File, io::{BufReader, BufRead}, collections::HashSet, hash::Hash};
+use std::{fs::{self, File}, io::{self, BufReader, BufRead, BufWriter, Write}, collections::HashSet, fmt};
+
+use day1::raster::Rasterize;
 
 fn main() {
     let file = File::open("data/day14/input.txt").unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines();
 
-    let paths = lines.map(|x| parse_path(&x.unwrap())).collect::<Vec<_>>();
-
-    let max_y = paths.iter().flat_map(|p| p.iter().map(|pos| pos.y)).max().unwrap();
-
-    let mut obstacles = paths.iter().flat_map(|path| {
-        path.windows(2).flat_map(|window| {
-            assert_eq!(window.len(), 2);
-            let s = window[0];
-            let e = window[1];
-            if s.x == e.x {
-                let (start_y, end_y) = min_max(s.y, e.y);
-                (start_y..=end_y).map(|y| Position{x:s.x, y}).collect::<Vec<_>>()
-            } else if s.y == e.y {
-                let (start_x, end_x) = min_max(s.x, e.x);
-                (start_x..=end_x).map(|x| Position{x, y:s.y}).collect::<Vec<_>>()
-            } else {
-                panic!("diagonal line")
-            }
-        })
-    }).collect::<HashSet<_>>();
+    let options = parse_options();
+
+    let rocks = parse_paths(lines.map(|x| x.unwrap())).and_then(|paths| get_rocks(&paths, options.diagonals));
+    let rocks = match rocks {
+        Ok(rocks) => rocks,
+        Err(e) => {
+            eprintln!("bad rock path on {}", e);
+            std::process::exit(1);
+        }
+    };
 
+    let mut cave = Cave::new(&rocks, options.source, false);
+
+    if let Some(dir) = &options.ppm_dir {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("couldn't create {}: {}", dir, e);
+            std::process::exit(1);
+        }
+    }
 
     let mut count = 0;
-    while add_sand(&mut obstacles, max_y) {
+    while cave.add_sand() {
         count += 1;
+
+        if options.text_frames {
+            println!("{}", cave.render());
+        }
+        if let Some(dir) = &options.ppm_dir {
+            let path = format!("{}/frame_{:05}.ppm", dir, count);
+            if let Err(e) = File::create(&path).and_then(|file| cave.write_ppm(&mut BufWriter::new(file))) {
+                eprintln!("couldn't write {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if options.render {
+        print!("{}", cave.render());
     }
 
     println!("count: {}", count);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Air,
+    Rock,
+    Sand,
+}
+
+/// Dense grid covering just the part of the cave sand can reach.
+/// Also remembers the path the last grain fell down,
+/// since the next grain follows the same path until the point where the last one stopped.
+struct Cave {
+    grid: Grid<Cell>,
+    // world x of grid column 0
+    min_x: usize,
+    source: Position,
+    floor_y: Option<usize>,
+    fall_path: Vec<Position>,
+}
+
+impl Cave {
+    /// With a floor, sand piles up until it blocks the source.
+    /// Without one, sand that falls past the lowest rock is lost to the abyss.
+    fn new(rocks: &HashSet<Position>, source: Position, has_floor: bool) -> Self {
+        // With a floor the sand can spread out as far sideways as it falls,
+        // which may be further left than x=0, so move everything right to make room.
+        // Only the grid coordinates change, the picture stays the same.
+        let x_shift = if has_floor {
+            let floor_y = rocks.iter().map(|p| p.y).max().unwrap_or(0).max(source.y) + 2;
+            (floor_y - source.y).saturating_sub(source.x)
+        } else {
+            0
+        };
+        let rocks = rocks.iter().map(|p| Position{x: p.x + x_shift, y: p.y}).collect::<HashSet<_>>();
+        let source = Position{x: source.x + x_shift, y: source.y};
+
+        let max_y = rocks.iter().map(|p| p.y).max().unwrap_or(0).max(source.y);
+        let rocks_min_x = rocks.iter().map(|p| p.x).min().unwrap_or(source.x).min(source.x);
+        let rocks_max_x = rocks.iter().map(|p| p.x).max().unwrap_or(source.x).max(source.x);
+
+        let (floor_y, min_x, max_x, height) = if has_floor {
+            // sand can only ever spread out into a triangle below the source
+            let floor_y = max_y + 2;
+            let spread = floor_y - source.y;
+            (Some(floor_y), rocks_min_x.min(source.x - spread), rocks_max_x.max(source.x + spread), floor_y)
+        }
+        else {
+            (None, rocks_min_x, rocks_max_x, max_y + 1)
+        };
+
+        let mut grid = Grid::new(max_x - min_x + 1, height, Cell::Air);
+        for rock in &rocks {
+            grid.set(rock.x - min_x, rock.y, Cell::Rock);
+        }
+
+        Self { grid, min_x, source, floor_y, fall_path: vec![source] }
+    }
+
+    /// None means the position is off into the abyss.
+    fn get(&self, pos: Position) -> Option<Cell> {
+        if self.floor_y == Some(pos.y) {
+            return Some(Cell::Rock);
+        }
+        if pos.x < self.min_x {
+            return None;
+        }
+        self.grid.try_get(pos.x - self.min_x, pos.y).copied()
+    }
+
+    fn set(&mut self, pos: Position, cell: Cell) {
+        self.grid.set(pos.x - self.min_x, pos.y, cell);
+    }
+
+    fn get_display_char(&self, pos: Position) -> char {
+        match self.get(pos).unwrap() {
+            Cell::Rock => '#',
+            Cell::Sand => 'o',
+            Cell::Air if pos == self.source => '+',
+            Cell::Air => '.',
+        }
+    }
+
+    /// Draws the cave like the puzzle does, including the floor if there is one.
+    fn render(&self) -> String {
+        let height = self.floor_y.map_or(self.grid.height(), |y| y + 1);
 
-/// Returns true if the sand stopped before it passed the cutoff line.
-fn add_sand(obstacles: &mut HashSet<Position>, cutoff_y: usize) -> bool {
-    let mut pos = Position{x: 500, y: 0};
-    loop {
-        let next_pos = next_sand_pos(obstacles, pos);
-        match next_pos {
-            None => {
-                obstacles.insert(pos);
-                return true;
+        let mut s = String::new();
+        for y in 0..height {
+            for x in 0..self.grid.width {
+                s.push(self.get_display_char(Position{x: x + self.min_x, y}));
             }
-            Some(p) if p.y > cutoff_y => {
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Same picture as render, as a binary PPM with one pixel per cell.
+    fn write_ppm<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let height = self.floor_y.map_or(self.grid.height(), |y| y + 1);
+
+        write!(w, "P6\n{} {}\n255\n", self.grid.width, height)?;
+        for y in 0..height {
+            for x in 0..self.grid.width {
+                let colour: [u8; 3] = match self.get_display_char(Position{x: x + self.min_x, y}) {
+                    '#' => [110, 110, 110],
+                    'o' => [230, 200, 90],
+                    '+' => [220, 40, 40],
+                    _ => [20, 20, 35],
+                };
+                w.write_all(&colour)?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Returns true if a grain of sand came to rest,
+    /// false if sand is now falling into the abyss or the source is blocked.
+    fn add_sand(&mut self) -> bool {
+        while let Some(&pos) = self.fall_path.last() {
+            if self.get(pos) != Some(Cell::Air) {
                 return false;
             }
-            Some(p) => { pos = p; }
+
+            let candidate_positions = [
+                Some(Position{x: pos.x, y: pos.y+1}),
+                pos.x.checked_sub(1).map(|x| Position{x, y: pos.y+1}),
+                Some(Position{x: pos.x+1, y: pos.y+1}),
+            ];
+
+            let mut next_pos = None;
+            for candidate in candidate_positions {
+                match candidate.and_then(|p| self.get(p).map(|c| (p, c))) {
+                    None => { return false; }
+                    Some((p, Cell::Air)) => {
+                        next_pos = Some(p);
+                        break;
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            match next_pos {
+                Some(p) => { self.fall_path.push(p); }
+                None => {
+                    self.set(pos, Cell::Sand);
+                    self.fall_path.pop();
+                    if self.fall_path.is_empty() {
+                        // leave the source on the path so we notice it's blocked next time
+                        self.fall_path.push(self.source);
+                    }
+                    return true;
+                }
+            }
         }
+
+        false
     }
 }
 
-fn next_sand_pos(obstacles: &HashSet<Position>, pos: Position) -> Option<Position> {
-    let candidate_positions = [
-        Position{x: pos.x, y: pos.y+1},
-        Position{x: pos.x-1, y: pos.y+1},
-        Position{x: pos.x+1, y: pos.y+1},
-    ];
-    candidate_positions.into_iter().find(|p| !obstacles.contains(p))
+#[derive(Debug)]
+struct Grid<T: Clone> {
+    width: usize,
+    vec: Vec<T>,
 }
 
+impl<T: Clone> Grid<T> {
+    fn new(width: usize, height: usize, val: T) -> Self {
+        Self { width, vec: vec![val; width*height] }
+    }
+
+    fn height(&self) -> usize {
+        self.vec.len() / self.width
+    }
 
-fn min_max(a: usize, b: usize) -> (usize, usize) {
-    if a < b { (a, b) } else { (b, a) }
+    fn is_in_bounds(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height()
+    }
+
+    fn try_get(&self, x: usize, y: usize) -> Option<&T> {
+        if self.is_in_bounds(x, y) {
+            Some(&self.vec[(y*self.width)+x])
+        }
+        else {
+            None
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, val: T) {
+        assert!(self.is_in_bounds(x, y));
+        self.vec[(y*self.width)+x] = val;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct PathError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
 }
 
-fn parse_path(s: &str) -> Vec<Position> {
-    s.split(" -> ").map(|item| {
-        let parts = item.split(',').map(|n| n.parse().unwrap()).collect::<Vec<_>>();
-        assert!(parts.len() == 2);
-        Position{x: parts[0], y: parts[1]}
-    }).collect()
+/// Diagonal segments are only allowed if asked for, and must be at 45 degrees.
+fn get_rocks(paths: &[Vec<Position>], allow_diagonals: bool) -> Result<HashSet<Position>, PathError> {
+    let mut rocks = HashSet::new();
+    for (i, path) in paths.iter().enumerate() {
+        let cells = path.rasterize(allow_diagonals).map_err(|e| PathError{line: i+1, message: e.to_string()})?;
+        rocks.extend(cells);
+    }
+    Ok(rocks)
+}
+
+fn parse_position(s: &str) -> Result<Position, String> {
+    let (x, y) = s.trim().split_once(',').ok_or_else(|| format!("expected x,y but got '{}'", s.trim()))?;
+    let parse_coord = |n: &str| n.trim().parse().map_err(|_| format!("invalid coordinate '{}'", n.trim()));
+    Ok(Position{x: parse_coord(x)?, y: parse_coord(y)?})
+}
+
+fn parse_path(s: &str) -> Result<Vec<Position>, String> {
+    s.split("->").map(parse_position).collect()
+}
+
+/// Line numbers in errors are 1-based, and paths are returned one per line.
+fn parse_paths<T: Iterator<Item=String>>(lines: T) -> Result<Vec<Vec<Position>>, PathError> {
+    lines.enumerate()
+        .map(|(i, line)| parse_path(&line).map_err(|message| PathError{line: i+1, message}))
+        .collect()
+}
+
+struct Options {
+    source: Position,
+    // print the cave once at the end
+    render: bool,
+    // print the cave every time a grain settles
+    text_frames: bool,
+    // write the cave as a numbered PPM image into this directory every time a grain settles
+    ppm_dir: Option<String>,
+    // allow rock paths to have 45 degree diagonal segments
+    diagonals: bool,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        source: Position{x: 500, y: 0},
+        render: false,
+        text_frames: false,
+        ppm_dir: None,
+        diagonals: false,
+    };
+
+    for arg in std::env::args().skip(1) {
+        if let Some(s) = arg.strip_prefix("--source=") {
+            options.source = parse_position(s).unwrap_or_else(|e| panic!("bad --source: {}", e));
+        }
+        else if let Some(s) = arg.strip_prefix("--ppm-frames=") {
+            options.ppm_dir = Some(s.to_string());
+        }
+        else if arg == "--render" {
+            options.render = true;
+        }
+        else if arg == "--frames" {
+            options.text_frames = true;
+        }
+        else if arg == "--diagonals" {
+            options.diagonals = true;
+        }
+        else {
+            panic!("unknown argument: {}", arg);
+        }
+    }
+
+    options
 }
 
 
@@ -82,3 +332,94 @@ struct Position {
     x: usize,
     y: usize,
 }
+
+impl From<(i64, i64)> for Position {
+    fn from((x, y): (i64, i64)) -> Self {
+        Position{x: x.try_into().unwrap(), y: y.try_into().unwrap()}
+    }
+}
+
+impl From<Position> for (i64, i64) {
+    fn from(p: Position) -> Self {
+        (p.x.try_into().unwrap(), p.y.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_sand_sample() {
+        let file = File::open("data/day14/sample_input.txt").unwrap();
+        let paths = parse_paths(BufReader::new(file).lines().map(|x| x.unwrap())).unwrap();
+
+        let mut cave = Cave::new(&get_rocks(&paths, false).unwrap(), Position{x: 500, y: 0}, false);
+        let mut count = 0;
+        while cave.add_sand() {
+            count += 1;
+        }
+        assert_eq!(count, 24);
+        assert!(!cave.add_sand());
+    }
+
+    #[test]
+    fn test_render_sample() {
+        let file = File::open("data/day14/sample_input.txt").unwrap();
+        let paths = parse_paths(BufReader::new(file).lines().map(|x| x.unwrap())).unwrap();
+
+        let mut cave = Cave::new(&get_rocks(&paths, false).unwrap(), Position{x: 500, y: 0}, false);
+        assert_eq!(cave.render(), "\
+......+...
+..........
+..........
+..........
+....#...##
+....#...#.
+..###...#.
+........#.
+........#.
+#########.
+");
+
+        while cave.add_sand() {}
+        assert_eq!(cave.render(), "\
+......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########.
+");
+
+        let mut ppm = Vec::new();
+        cave.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n10 10\n255\n"));
+        assert_eq!(ppm.len(), "P6\n10 10\n255\n".len() + (10 * 10 * 3));
+    }
+
+    #[test]
+    fn test_parse_paths_errors() {
+        let lines = ["498,4 -> 498,6", "503,4 -> 502 4"].map(|s| s.to_string());
+        assert_eq!(parse_paths(lines.into_iter()), Err(PathError{line: 2, message: "expected x,y but got '502 4'".to_string()}));
+
+        let lines = ["498,4 -> x,6"].map(|s| s.to_string());
+        assert_eq!(parse_paths(lines.into_iter()), Err(PathError{line: 1, message: "invalid coordinate 'x'".to_string()}));
+    }
+
+    #[test]
+    fn test_get_rocks_diagonals() {
+        let paths = parse_paths(["1,1 -> 1,2", "0,0 -> 2,2 -> 4,0"].map(|s| s.to_string()).into_iter()).unwrap();
+
+        let err = get_rocks(&paths, false).unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let rocks = get_rocks(&paths, true).unwrap();
+        assert_eq!(rocks.len(), 6);
+        assert!(rocks.contains(&Position{x: 3, y: 1}));
+    }
+}
@@ -3,16 +3,26 @@ use std::{fs::File, io::{BufReader, BufRead}, collections::HashSet};
 use lazy_static::lazy_static;
 use regex::Regex;
 
+const INPUT_PATH: &str = "data/day15/input.txt";
+const SAMPLE_INPUT_PATH: &str = "data/day15/sample_input.txt";
+
 fn main() {
-    let file = File::open("data/day15/input.txt").unwrap();
+    let options = parse_options();
+
+    let file = File::open(&options.input_path).unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines();
 
-    //let target_row_y = 10;
-    let target_row_y = 2000000;
-
     let sensor_lines = lines.map(|x| parse_sensor_line(&x.unwrap())).collect::<Vec<_>>();
 
+    if let Some((min, max)) = options.render_window {
+        print!("{}", render(&sensor_lines, min, max));
+    }
+
+    println!("{}", count_covered_cells(&sensor_lines, options.target_row_y));
+}
+
+fn count_covered_cells(sensor_lines: &[SensorLine], target_row_y: i32) -> i32 {
     let mut intervals = sensor_lines.iter().flat_map(|l| {
         let sensor_range = get_manhattan_distance(l.sensor_position, l.closest_beacon_position);
         get_row_range_covered(l.sensor_position, sensor_range, target_row_y)
@@ -27,7 +37,73 @@ fn main() {
     let beacons_in_range = beacon_positions.iter().filter(|p| p.y == target_row_y && intervals.iter().any(|interval| interval.contains_val(p.x))).count();
     covered_cell_count -= i32::try_from(beacons_in_range).unwrap();
 
-    println!("{}", covered_cell_count);
+    covered_cell_count
+}
+
+/// Draws sensors, beacons and covered cells in the window between min and max inclusive,
+/// like the pictures in the puzzle.
+fn render(sensor_lines: &[SensorLine], min: Position, max: Position) -> String {
+    let ranges = sensor_lines.iter()
+        .map(|l| (l.sensor_position, get_manhattan_distance(l.sensor_position, l.closest_beacon_position)))
+        .collect::<Vec<_>>();
+
+    let mut s = String::new();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            let pos = Position{x, y};
+            let c = if sensor_lines.iter().any(|l| l.sensor_position == pos) { 'S' }
+                else if sensor_lines.iter().any(|l| l.closest_beacon_position == pos) { 'B' }
+                else if ranges.iter().any(|(sensor, range)| get_manhattan_distance(*sensor, pos) <= *range) { '#' }
+                else { '.' };
+            s.push(c);
+        }
+        s.push('\n');
+    }
+    s
+}
+
+struct Options {
+    input_path: String,
+    target_row_y: i32,
+    // corners of the area to draw, if any
+    render_window: Option<(Position, Position)>,
+}
+
+fn parse_options() -> Options {
+    let mut input_path = INPUT_PATH.to_string();
+    let mut target_row_y = None;
+    // the sample asks about a different row to the real input
+    let mut default_target_row_y = 2000000;
+    let mut render_window = None;
+
+    for arg in std::env::args().skip(1) {
+        if arg == "--sample" {
+            input_path = SAMPLE_INPUT_PATH.to_string();
+            default_target_row_y = 10;
+        }
+        else if let Some(s) = arg.strip_prefix("--input=") {
+            input_path = s.to_string();
+        }
+        else if let Some(s) = arg.strip_prefix("--row=") {
+            target_row_y = Some(s.parse().unwrap());
+        }
+        else if let Some(s) = arg.strip_prefix("--render=") {
+            let (min, max) = s.split_once(':').expect("expected --render=min_x,min_y:max_x,max_y");
+            render_window = Some((parse_position(min), parse_position(max)));
+        }
+        else {
+            panic!("unknown argument: {}", arg);
+        }
+    }
+
+    let target_row_y = target_row_y.unwrap_or(default_target_row_y);
+
+    Options { input_path, target_row_y, render_window }
+}
+
+fn parse_position(s: &str) -> Position {
+    let (x, y) = s.split_once(',').unwrap();
+    Position{x: x.parse().unwrap(), y: y.parse().unwrap()}
 }
 
 lazy_static! {
@@ -153,4 +229,19 @@ mod tests {
         assert_eq!(get_row_range_covered(Position{x:0,y:0}, 5, 5), Some(Interval{first:0, last:0}));
         assert_eq!(get_row_range_covered(Position{x:3,y:0}, 5, 5), Some(Interval{first:3, last:3}));
     }
+
+    #[test]
+    fn test_sample() {
+        let file = File::open(SAMPLE_INPUT_PATH).unwrap();
+        let sensor_lines = BufReader::new(file).lines().map(|x| parse_sensor_line(&x.unwrap())).collect::<Vec<_>>();
+
+        assert_eq!(count_covered_cells(&sensor_lines, 10), 26);
+
+        // the distress beacon is the gap at 14,11
+        assert_eq!(render(&sensor_lines, Position{x: -2, y: 9}, Position{x: 25, y: 11}), "\
+.#########################..
+####B######################.
+##S#############.###########
+");
+    }
 }
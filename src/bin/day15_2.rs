@@ -1,34 +1,180 @@
-use std::{fs::File, io::{BufReader, BufRead}};
+use std::{fs::File, io::{BufReader, BufRead}, sync::atomic::{AtomicBool, Ordering}, thread, time::Instant};
 
 use lazy_static::lazy_static;
 use regex::Regex;
 
+const INPUT_PATH: &str = "data/day15/input.txt";
+const SAMPLE_INPUT_PATH: &str = "data/day15/sample_input.txt";
+
 fn main() {
-    let file = File::open("data/day15/input.txt").unwrap();
+    let options = parse_options();
+
+    let file = File::open(&options.input_path).unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines();
 
     let sensor_lines = lines.map(|x| parse_sensor_line(&x.unwrap())).collect::<Vec<_>>();
 
-    //let search_space_max = 20;
-    let search_space_max = 4000000;
+    let start_time = Instant::now();
+    let found = if options.use_boundaries {
+        find_beacon_by_boundaries(&sensor_lines, options.search_space_max)
+    }
+    else if options.thread_count > 1 {
+        find_beacon_by_rows_parallel(&sensor_lines, options.search_space_max, options.thread_count)
+    }
+    else {
+        find_beacon_by_rows(&sensor_lines, options.search_space_max)
+    };
+    let elapsed = start_time.elapsed();
+    println!("search took {:?}", elapsed);
+
+    if options.benchmark && !options.use_boundaries && options.thread_count > 1 {
+        let single_start_time = Instant::now();
+        let single_found = find_beacon_by_rows(&sensor_lines, options.search_space_max);
+        let single_elapsed = single_start_time.elapsed();
+        println!("single threaded search took {:?}, speedup with {} threads: {:.2}x",
+            single_elapsed, options.thread_count, single_elapsed.as_secs_f64() / elapsed.as_secs_f64());
+        // with more than one hole the threads can stop at a different one to the sequential scan
+        if found != single_found {
+            println!("single threaded search found {:?} instead", single_found);
+        }
+    }
 
-    for y in 0..=search_space_max {
-        let mut intervals = sensor_lines.iter().flat_map(|l| {
-            let sensor_range = get_manhattan_distance(l.sensor_position, l.closest_beacon_position);
-            get_row_range_covered(l.sensor_position, sensor_range, y)
-        }).collect::<Vec<_>>();
+    let pos = found.expect("not found!");
+    println!("found it: x: {}, y: {}", pos.x, pos.y);
+    let freq = (i64::from(pos.x) * 4000000) + i64::from(pos.y);
+    println!("freq: {}", freq);
+}
 
-        flatten_intervals(&mut intervals);
+struct Options {
+    input_path: String,
+    search_space_max: i32,
+    // intersect sensor boundaries rather than scanning every row
+    use_boundaries: bool,
+    // how many threads to split the row scan across
+    thread_count: usize,
+    // also time a single threaded scan to compare against
+    benchmark: bool,
+}
 
-        if let Some(x) = find_hole_in_intervals(0, search_space_max, &intervals) {
-            println!("found it: x: {}, y: {}", x, y);
-            let freq = (i64::from(x) * 4000000) + i64::from(y);
-            println!("freq: {}", freq);
-            return;
+fn parse_options() -> Options {
+    let mut input_path = INPUT_PATH.to_string();
+    let mut search_space_max = None;
+    // the sample has a much smaller search space than the real input
+    let mut default_search_space_max = 4000000;
+    let mut use_boundaries = false;
+    let mut thread_count = 1;
+    let mut benchmark = false;
+
+    for arg in std::env::args().skip(1) {
+        if arg == "--sample" {
+            input_path = SAMPLE_INPUT_PATH.to_string();
+            default_search_space_max = 20;
+        }
+        else if let Some(s) = arg.strip_prefix("--input=") {
+            input_path = s.to_string();
+        }
+        else if let Some(s) = arg.strip_prefix("--max=") {
+            search_space_max = Some(s.parse().unwrap());
+        }
+        else if arg == "--boundaries" {
+            use_boundaries = true;
+        }
+        else if let Some(s) = arg.strip_prefix("--threads=") {
+            thread_count = s.parse().unwrap();
+            assert!(thread_count > 0, "need at least one thread");
+        }
+        else if arg == "--benchmark" {
+            benchmark = true;
+        }
+        else {
+            panic!("unknown argument: {}", arg);
         }
     }
-    panic!("not found!");
+
+    let search_space_max = search_space_max.unwrap_or(default_search_space_max);
+
+    Options { input_path, search_space_max, use_boundaries, thread_count, benchmark }
+}
+
+fn find_hole_in_row(sensor_lines: &[SensorLine], search_space_max: i32, y: i32) -> Option<i32> {
+    let mut intervals = sensor_lines.iter().flat_map(|l| {
+        let sensor_range = get_manhattan_distance(l.sensor_position, l.closest_beacon_position);
+        get_row_range_covered(l.sensor_position, sensor_range, y)
+    }).collect::<Vec<_>>();
+
+    flatten_intervals(&mut intervals);
+
+    find_hole_in_intervals(0, search_space_max, &intervals)
+}
+
+fn find_beacon_by_rows(sensor_lines: &[SensorLine], search_space_max: i32) -> Option<Position> {
+    (0..=search_space_max).find_map(|y| find_hole_in_row(sensor_lines, search_space_max, y).map(|x| Position{x, y}))
+}
+
+/// Same as find_beacon_by_rows but each thread scans its own contiguous block of rows.
+/// Everyone gives up as soon as one of them finds the hole.
+fn find_beacon_by_rows_parallel(sensor_lines: &[SensorLine], search_space_max: i32, thread_count: usize) -> Option<Position> {
+    let row_count = usize::try_from(search_space_max).unwrap() + 1;
+    let rows_per_thread = row_count.div_ceil(thread_count);
+    let found = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let handles = (0..thread_count).map(|i| {
+            let first_y = i32::try_from((i * rows_per_thread).min(row_count)).unwrap();
+            let end_y = i32::try_from(((i + 1) * rows_per_thread).min(row_count)).unwrap();
+            let found = &found;
+            scope.spawn(move || {
+                for y in first_y..end_y {
+                    if found.load(Ordering::Relaxed) {
+                        return None;
+                    }
+                    if let Some(x) = find_hole_in_row(sensor_lines, search_space_max, y) {
+                        found.store(true, Ordering::Relaxed);
+                        return Some(Position{x, y});
+                    }
+                }
+                None
+            })
+        }).collect::<Vec<_>>();
+
+        handles.into_iter().filter_map(|h| h.join().unwrap()).next()
+    })
+}
+
+/// The only uncovered cell has to sit just outside the edge of some sensors' ranges.
+/// Each sensor's range is a diamond, whose outline one step further out lies on
+/// two lines of x+y = c and two lines of x-y = c.
+/// In the middle of the search space the cell is where two of those lines cross,
+/// on the edge of the search space it's where one of them meets the edge, and otherwise it's a corner.
+fn find_beacon_by_boundaries(sensor_lines: &[SensorLine], search_space_max: i32) -> Option<Position> {
+    let ranges = sensor_lines.iter()
+        .map(|l| (l.sensor_position, get_manhattan_distance(l.sensor_position, l.closest_beacon_position)))
+        .collect::<Vec<_>>();
+
+    let mut sum_lines = Vec::new();
+    let mut diff_lines = Vec::new();
+    for (pos, range) in &ranges {
+        let outside = i64::from(*range) + 1;
+        let (x, y) = (i64::from(pos.x), i64::from(pos.y));
+        sum_lines.extend([x + y - outside, x + y + outside]);
+        diff_lines.extend([x - y - outside, x - y + outside]);
+    }
+
+    let intersections = sum_lines.iter().flat_map(|a| diff_lines.iter().map(move |b| (a, b)))
+        .filter(|(a, b)| (*a + *b) % 2 == 0)
+        .map(|(a, b)| ((a + b) / 2, (a - b) / 2));
+
+    let max = i64::from(search_space_max);
+    let sum_line_edges = sum_lines.iter().flat_map(|c| [(0, *c), (max, c - max), (*c, 0), (c - max, max)]);
+    let diff_line_edges = diff_lines.iter().flat_map(|c| [(0, -c), (max, max - c), (*c, 0), (c + max, max)]);
+
+    let corners = [(0, 0), (0, max), (max, 0), (max, max)];
+
+    intersections.chain(sum_line_edges).chain(diff_line_edges).chain(corners)
+        .filter(|&(x, y)| (0..=i64::from(search_space_max)).contains(&x) && (0..=i64::from(search_space_max)).contains(&y))
+        .map(|(x, y)| Position{x: x.try_into().unwrap(), y: y.try_into().unwrap()})
+        .find(|p| ranges.iter().all(|(sensor, range)| get_manhattan_distance(*sensor, *p) > *range))
 }
 
 lazy_static! {
@@ -77,15 +223,14 @@ impl Interval {
     // }
 }
 
+/// Expects flattened intervals. Gaps outside min..=max don't count.
 fn find_hole_in_intervals(min: i32, max: i32, intervals: &[Interval]) -> Option<i32> {
-    if intervals.is_empty() || intervals[0].first > min { return Some(min); }
-    if intervals[intervals.len()-1].last < max { return Some(intervals[intervals.len()-1].last + 1); }
-
-    intervals.windows(2).find_map(|slice| {
-        let a = slice[0];
-        let b = slice[1];
-        if a.last + 1 < b.first { Some(a.last + 1) } else { None }
-    })
+    let mut x = min;
+    for interval in intervals {
+        if interval.first > x { break; }
+        x = x.max(interval.last + 1);
+    }
+    if x <= max { Some(x) } else { None }
 }
 
 fn flatten_intervals(intervals: &mut Vec<Interval>) {
@@ -163,9 +308,112 @@ mod tests {
         assert_eq!(get_manhattan_distance(Position{x:2, y:1}, Position{x:-4, y:-1}), 8);
     }
 
+    #[test]
+    fn test_find_hole_in_intervals() {
+        let intervals = [Interval{first: -3, last: -2}, Interval{first: 0, last: 4}, Interval{first: 6, last: 9}];
+        assert_eq!(find_hole_in_intervals(0, 9, &intervals), Some(5));
+        assert_eq!(find_hole_in_intervals(-1, 9, &intervals), Some(-1));
+        assert_eq!(find_hole_in_intervals(0, 4, &intervals), None);
+        assert_eq!(find_hole_in_intervals(6, 10, &intervals), Some(10));
+        assert_eq!(find_hole_in_intervals(10, 12, &intervals), Some(10));
+        assert_eq!(find_hole_in_intervals(0, 9, &[]), Some(0));
+    }
+
     #[test]
     fn test_get_row_range_covered() {
         assert_eq!(get_row_range_covered(Position{x:0,y:0}, 5, 5), Some(Interval{first:0, last:0}));
         assert_eq!(get_row_range_covered(Position{x:3,y:0}, 5, 5), Some(Interval{first:3, last:3}));
     }
+
+    fn read_sensor_lines(path: &str) -> Vec<SensorLine> {
+        let file = File::open(path).unwrap();
+        BufReader::new(file).lines().map(|x| parse_sensor_line(&x.unwrap())).collect()
+    }
+
+    #[test]
+    fn test_find_beacon_sample() {
+        let sensor_lines = read_sensor_lines(SAMPLE_INPUT_PATH);
+        assert_eq!(find_beacon_by_rows(&sensor_lines, 20), Some(Position{x: 14, y: 11}));
+        assert_eq!(find_beacon_by_boundaries(&sensor_lines, 20), Some(Position{x: 14, y: 11}));
+        for thread_count in [1, 2, 3, 8, 30] {
+            assert_eq!(find_beacon_by_rows_parallel(&sensor_lines, 20, thread_count), Some(Position{x: 14, y: 11}));
+        }
+    }
+
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, max: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % max
+        }
+    }
+
+    fn sensor_line(x: i32, y: i32, range: i32) -> SensorLine {
+        SensorLine{sensor_position: Position{x, y}, closest_beacon_position: Position{x: x + range, y}}
+    }
+
+    /// Random sensors that each stop just short of the hole, topped up until the hole is the only cell left uncovered.
+    fn random_sensor_lines(rng: &mut Rng, search_space_max: i32, hole: Position) -> Vec<SensorLine> {
+        let is_covered = |sensor_lines: &[SensorLine], p: Position| sensor_lines.iter()
+            .any(|l| get_manhattan_distance(l.sensor_position, p) <= get_manhattan_distance(l.sensor_position, l.closest_beacon_position));
+        let cells = (0..=search_space_max).flat_map(|y| (0..=search_space_max).map(move |x| Position{x, y}))
+            .filter(|p| *p != hole)
+            .collect::<Vec<_>>();
+
+        let spread = u64::try_from(search_space_max).unwrap() + 7;
+        let mut sensor_lines = Vec::new();
+        for _ in 0..6 {
+            let x = i32::try_from(rng.next(spread)).unwrap() - 3;
+            let y = i32::try_from(rng.next(spread)).unwrap() - 3;
+            let range = i32::try_from(get_manhattan_distance(Position{x, y}, hole)).unwrap() - 1;
+            if range >= 0 {
+                sensor_lines.push(sensor_line(x, y, range));
+            }
+        }
+        for p in &cells {
+            if !is_covered(&sensor_lines, *p) {
+                let range = i32::try_from(get_manhattan_distance(*p, hole)).unwrap() - 1;
+                sensor_lines.push(sensor_line(p.x, p.y, range));
+            }
+        }
+        assert!(cells.iter().all(|p| is_covered(&sensor_lines, *p)));
+        assert!(!is_covered(&sensor_lines, hole));
+        sensor_lines
+    }
+
+    #[test]
+    fn test_find_beacon_synthetic() {
+        let mut rng = Rng(0x1234_5678);
+        for search_space_max in [1, 2, 5, 9] {
+            for hole_y in 0..=search_space_max {
+                for hole_x in 0..=search_space_max {
+                    let hole = Position{x: hole_x, y: hole_y};
+                    let sensor_lines = random_sensor_lines(&mut rng, search_space_max, hole);
+                    assert_eq!(find_beacon_by_rows(&sensor_lines, search_space_max), Some(hole), "{:?}", sensor_lines);
+                    assert_eq!(find_beacon_by_boundaries(&sensor_lines, search_space_max), Some(hole), "{:?}", sensor_lines);
+                    assert_eq!(find_beacon_by_rows_parallel(&sensor_lines, search_space_max, 3), Some(hole), "{:?}", sensor_lines);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_beacon_in_corner() {
+        // everything but (0,0) is covered by a single sensor
+        let sensor_lines = [SensorLine{sensor_position: Position{x: 3, y: 3}, closest_beacon_position: Position{x: 3, y: 8}}];
+        assert_eq!(find_beacon_by_rows(&sensor_lines, 5), Some(Position{x: 0, y: 0}));
+        assert_eq!(find_beacon_by_boundaries(&sensor_lines, 5), Some(Position{x: 0, y: 0}));
+    }
+
+    #[test]
+    fn test_find_beacon_on_edge() {
+        // everything but (5,6) is covered, and no two boundaries cross there
+        let sensor_lines = [(2, 2, 6), (-1, 3, 3), (10, 7, 5), (9, 5, 3)]
+            .map(|(x, y, range)| SensorLine{sensor_position: Position{x, y}, closest_beacon_position: Position{x: x + range, y}});
+        assert_eq!(find_beacon_by_rows(&sensor_lines, 6), Some(Position{x: 5, y: 6}));
+        assert_eq!(find_beacon_by_boundaries(&sensor_lines, 6), Some(Position{x: 5, y: 6}));
+    }
 }
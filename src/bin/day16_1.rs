@@ -1,5 +1,6 @@
 use std::{fs::File, io::{BufReader, BufRead}, collections::{HashMap, VecDeque, HashSet}};
 
+use day1::search::bfs;
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -13,12 +14,21 @@ fn main() {
 
     let complete_graph = get_complete_graph(&graph);
 
-    let best_score = find_best_score_dfs(&complete_graph, State {
+    let show_timeline = std::env::args().any(|a| a == "--timeline");
+
+    let total_minutes = 30;
+    let (best_score, plan) = find_best_plan_dfs(&complete_graph, total_minutes, State {
         activated_vertices: HashSet::new(),
         current_vertex: "AA".to_string(),
-        minutes_remaining: 30,
+        minutes_remaining: total_minutes,
     });
 
+    print!("{}", render_plan("You", &plan));
+    if show_timeline {
+        println!();
+        print!("{}", render_timeline(&graph, "AA", total_minutes, &[("You", &plan)]));
+    }
+
     println!("best score: {}", best_score);
 }
 
@@ -51,6 +61,7 @@ struct State {
 struct Entry {
     state: State,
     score: i32,
+    plan: Vec<PlanStep>,
 }
 
 #[derive(Debug)]
@@ -65,23 +76,11 @@ struct WeightedGraph {
 }
 
 fn get_neighbour_distances(graph: &Graph, vertex: &str) -> HashMap<String, i32> {
-    let mut visited = HashMap::<String, i32>::new();
-    let mut queue = VecDeque::<(i32, String)>::new();
-
-    queue.push_back((0, vertex.to_string()));
-    
-    while let Some((cost, vert)) = queue.pop_front() {
-        visited.insert(vert.clone(), cost);
-
-        for succ in &graph.vertices[&vert].neighbours {
-            if visited.contains_key(succ) {
-                continue;
-            }
-            queue.push_back((cost+1, succ.clone()));
-        }
-    }
-
-    visited
+    bfs([vertex.to_string()], |vert| graph.vertices[vert].neighbours.iter().cloned(), |_| false)
+        .distances
+        .into_iter()
+        .map(|(vert, cost)| (vert, cost as i32))
+        .collect()
 }
 
 fn get_complete_graph(graph: &Graph) -> WeightedGraph {
@@ -98,7 +97,7 @@ fn get_complete_graph(graph: &Graph) -> WeightedGraph {
     WeightedGraph { vertices: vertices.collect() }
 }
 
-fn get_successors_complete(graph: &WeightedGraph, e: &Entry) -> Vec<Entry> {
+fn get_successors_complete(graph: &WeightedGraph, total_minutes: i32, e: &Entry) -> Vec<Entry> {
     let mut vec = Vec::new();
 
     if e.state.minutes_remaining == 0 {
@@ -121,13 +120,21 @@ fn get_successors_complete(graph: &WeightedGraph, e: &Entry) -> Vec<Entry> {
         }
         let mut new_activated_vertices = e.state.activated_vertices.clone();
         new_activated_vertices.insert(neighbour.clone());
+        let pressure_released = neighbour_vertex.flow_rate * new_minutes_remaining;
+        let mut new_plan = e.plan.clone();
+        new_plan.push(PlanStep {
+            valve: neighbour.clone(),
+            minute: total_minutes - new_minutes_remaining,
+            pressure_released,
+        });
         vec.push(Entry {
-            score: e.score + (neighbour_vertex.flow_rate * new_minutes_remaining),
+            score: e.score + pressure_released,
             state: State {
                 current_vertex: neighbour.clone(),
                 activated_vertices: new_activated_vertices,
                 minutes_remaining: new_minutes_remaining,
-            }
+            },
+            plan: new_plan,
         })
     }
 
@@ -143,40 +150,130 @@ fn get_remaining_potential_score_complete(graph: &WeightedGraph, state: &State)
     remaining_valve_scores.reverse();
 
     (0..).map(|x| state.minutes_remaining - (2*x)).take_while(|x| *x > 0)
-    .zip(remaining_valve_scores.into_iter())
+    .zip(remaining_valve_scores)
     .map(|(t, s)| t * s).sum::<i32>()
 }
 
-fn find_best_score_dfs(graph: &WeightedGraph, initial_state: State) -> i32 {
+fn find_best_plan_dfs(graph: &WeightedGraph, total_minutes: i32, initial_state: State) -> (i32, Vec<PlanStep>) {
     let mut queue = VecDeque::<(i32, Entry)>::new();
 
-    queue.push_back((get_remaining_potential_score_complete(graph, &initial_state), Entry{state: initial_state, score: 0}));
+    queue.push_back((get_remaining_potential_score_complete(graph, &initial_state), Entry{state: initial_state, score: 0, plan: Vec::new()}));
 
     let mut best_seen_score = 0;
+    let mut best_plan = Vec::new();
 
     loop {
         match queue.pop_back() {
-            None => { return best_seen_score; }
+            None => { return (best_seen_score, best_plan); }
             Some((s, candidate)) => {
                 if best_seen_score > s {
                     continue;
                 }
 
-                best_seen_score = best_seen_score.max(candidate.score);
-
-
-                let mut successors = get_successors_complete(graph, &candidate).into_iter().map(|succ| {
+                let mut successors = get_successors_complete(graph, total_minutes, &candidate).into_iter().map(|succ| {
                     let potential_score = succ.score + get_remaining_potential_score_complete(graph, &succ.state);
                     (potential_score, succ)
                 }).collect::<Vec<_>>();
                 successors.sort_by_key(|x| x.1.score);
 
+                if candidate.score > best_seen_score {
+                    best_seen_score = candidate.score;
+                    best_plan = candidate.plan;
+                }
+
                 queue.extend(successors);
             }
         }
     }
 }
 
+fn get_route(graph: &Graph, from: &str, to: &str) -> Vec<String> {
+    bfs([from.to_string()], |v| graph.vertices[v].neighbours.iter().cloned(), |v| v == to).goal_path().unwrap()
+}
+
+fn get_flow_rate(graph: &Graph, valve: &str) -> i32 {
+    graph.vertices[valve].flow_rate
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlanStep {
+    valve: String,
+    // minute in which the valve gets opened, counting from 1
+    minute: i32,
+    // over the rest of the time
+    pressure_released: i32,
+}
+
+fn render_plan(actor: &str, plan: &[PlanStep]) -> String {
+    let mut s = format!("{}:\n", actor);
+    for step in plan {
+        s.push_str(&format!("  minute {:>2}: open valve {}, releasing {} pressure\n", step.minute, step.valve, step.pressure_released));
+    }
+    s
+}
+
+fn describe_open_valves(open_valves: &[(&str, i32)]) -> String {
+    let names = open_valves.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    let total = open_valves.iter().map(|(_, flow_rate)| flow_rate).sum::<i32>();
+    match names.len() {
+        0 => "No valves are open.".to_string(),
+        1 => format!("Valve {} is open, releasing {} pressure.", names[0], total),
+        2 => format!("Valves {} and {} are open, releasing {} pressure.", names[0], names[1], total),
+        n => format!("Valves {}, and {} are open, releasing {} pressure.", names[..n-1].join(", "), names[n-1], total),
+    }
+}
+
+fn conjugate(actor: &str, verb: &str) -> String {
+    if actor == "You" { verb.to_string() } else { format!("{}s", verb) }
+}
+
+/// Minute-by-minute walkthrough of the plans, in the same words as the puzzle.
+fn render_timeline(graph: &Graph, start: &str, total_minutes: i32, actors: &[(&str, &[PlanStep])]) -> String {
+    let minute_count = usize::try_from(total_minutes).unwrap();
+
+    // what each actor is doing in each minute
+    let mut actions = vec![vec![None; minute_count]; actors.len()];
+    for (i, (actor, plan)) in actors.iter().enumerate() {
+        let mut current = start.to_string();
+        let mut minute = 1;
+        for step in plan.iter() {
+            let route = get_route(graph, &current, &step.valve);
+            for hop in &route[1..] {
+                actions[i][minute - 1] = Some(format!("{} {} to valve {}.", actor, conjugate(actor, "move"), hop));
+                minute += 1;
+            }
+            assert_eq!(i32::try_from(minute).unwrap(), step.minute);
+            actions[i][minute - 1] = Some(format!("{} {} valve {}.", actor, conjugate(actor, "open"), step.valve));
+            minute += 1;
+            current = step.valve.clone();
+        }
+    }
+
+    let mut opened = actors.iter()
+        .flat_map(|(_, plan)| plan.iter())
+        .map(|step| (step.valve.as_str(), step.minute, get_flow_rate(graph, &step.valve)))
+        .collect::<Vec<_>>();
+    opened.sort();
+
+    let mut s = String::new();
+    for minute in 1..=total_minutes {
+        s.push_str(&format!("== Minute {} ==\n", minute));
+
+        let open_valves = opened.iter().filter(|(_, m, _)| *m < minute).map(|(name, _, flow_rate)| (*name, *flow_rate)).collect::<Vec<_>>();
+        s.push_str(&describe_open_valves(&open_valves));
+        s.push('\n');
+
+        for actor_actions in &actions {
+            if let Some(action) = &actor_actions[usize::try_from(minute).unwrap() - 1] {
+                s.push_str(action);
+                s.push('\n');
+            }
+        }
+        s.push('\n');
+    }
+    s
+}
+
 fn parse_graph<T: Iterator<Item=GraphLine>>(lines: T) -> Graph {
     let mut m = HashMap::new();
     for line in lines {
@@ -196,4 +293,55 @@ fn parse_graph_line(line: &str) -> GraphLine {
         flow_rate: captures[2].parse().unwrap(),
         neighbours: captures[3].split(", ").map(|s| s.to_string()).collect(),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_sample_graph() -> Graph {
+        let file = File::open("data/day16/sample_input.txt").unwrap();
+        parse_graph(BufReader::new(file).lines().map(|x| parse_graph_line(&x.unwrap())))
+    }
+
+    #[test]
+    fn test_find_best_plan_sample() {
+        let graph = read_sample_graph();
+        let (score, plan) = find_best_plan_dfs(&get_complete_graph(&graph), 30, State {
+            activated_vertices: HashSet::new(),
+            current_vertex: "AA".to_string(),
+            minutes_remaining: 30,
+        });
+
+        assert_eq!(score, 1651);
+        assert_eq!(plan.iter().map(|s| (s.valve.as_str(), s.minute)).collect::<Vec<_>>(),
+            vec![("DD", 2), ("BB", 5), ("JJ", 9), ("HH", 17), ("EE", 21), ("CC", 24)]);
+        assert_eq!(plan.iter().map(|s| s.pressure_released).sum::<i32>(), 1651);
+
+        let timeline = render_timeline(&graph, "AA", 30, &[("You", &plan)]);
+        assert!(timeline.starts_with("\
+== Minute 1 ==
+No valves are open.
+You move to valve DD.
+
+== Minute 2 ==
+No valves are open.
+You open valve DD.
+
+== Minute 3 ==
+Valve DD is open, releasing 20 pressure.
+You move to valve CC.
+
+"));
+        assert!(timeline.contains("\
+== Minute 20 ==
+Valves BB, DD, HH, and JJ are open, releasing 76 pressure.
+You move to valve EE.
+"));
+        assert!(timeline.ends_with("\
+== Minute 30 ==
+Valves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure.
+
+"));
+    }
+}
@@ -1,9 +1,12 @@
-use std::{fs::File, io::{BufReader, BufRead}, collections::{HashMap, VecDeque, BTreeSet}, mem::swap};
+use std::{fs::File, io::{BufReader, BufRead}, collections::{HashMap, BTreeMap}, time::Instant};
 
+use day1::search::bfs;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 fn main() {
+    let options = parse_options();
+
     let file = File::open("data/day16/input.txt").unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines();
@@ -11,23 +14,156 @@ fn main() {
     let graph_lines = lines.map(|x| parse_graph_line(&x.unwrap()));
     let graph = parse_graph(graph_lines);
 
-    let complete_graph = get_complete_graph(&graph, decode_vertex_name("AA"));
-
-    let best_score = find_best_score_rec_wrapper(&complete_graph, &State {
-        activated_vertices: BTreeSet::new(),
-        me: ActorState{
-            current_vertex: decode_vertex_name("AA"),
-            minutes_remaining: 26,
-        },
-        elephant: ActorState{
-            current_vertex: decode_vertex_name("AA"),
-            minutes_remaining: 26,
-        },
-    });
+    let start_vertex = decode_vertex_name(&options.start_valve);
+    let complete_graph = get_complete_graph(&graph, start_vertex);
+
+    // Teaching the helpers takes time away from everyone.
+    let available_minutes = if options.actor_count > 1 {
+        options.total_minutes - options.training_minutes
+    } else {
+        options.total_minutes
+    };
+
+    let initial_state = State {
+        activated_vertices: VertexSet::default(),
+        actors: vec![ActorState{ current_vertex: start_vertex, minutes_remaining: available_minutes }; options.actor_count],
+    };
+
+    let use_bitmask = options.solver == Solver::Bitmask;
+
+    let start_time = Instant::now();
+    let (best_score, plans) = if use_bitmask {
+        find_best_plans_bitmask(&complete_graph, start_vertex, available_minutes, options.actor_count)
+    } else {
+        find_best_plans(&complete_graph, available_minutes, &initial_state)
+    };
+    let elapsed = start_time.elapsed();
+
+    if options.benchmark {
+        println!("{} solver took {:?}", if use_bitmask { "bitmask" } else { "recursive" }, elapsed);
+
+        let other_start_time = Instant::now();
+        let other_score = if use_bitmask {
+            find_best_plans(&complete_graph, available_minutes, &initial_state).0
+        } else {
+            find_best_plans_bitmask(&complete_graph, start_vertex, available_minutes, options.actor_count).0
+        };
+        let other_elapsed = other_start_time.elapsed();
+        assert_eq!(best_score, other_score);
+        println!("{} solver took {:?}", if use_bitmask { "recursive" } else { "bitmask" }, other_elapsed);
+    }
+
+    let names = get_actor_names(options.actor_count);
+    let actors = names.iter().map(|n| n.as_str()).zip(plans.iter().map(|p| p.as_slice())).collect::<Vec<_>>();
+    for (actor, plan) in &actors {
+        print!("{}", render_plan(actor, plan));
+    }
+    if options.show_timeline {
+        println!();
+        print!("{}", render_timeline(&graph, &options.start_valve, available_minutes, &actors));
+    }
+
+    let routes = if options.highlight_route { plans.iter().map(|p| p.as_slice()).collect() } else { Vec::new() };
+    if let Some(path) = &options.dot_path {
+        std::fs::write(path, render_graph_dot(&graph, &options.start_valve, &routes)).unwrap();
+    }
+    if let Some(path) = &options.complete_dot_path {
+        std::fs::write(path, render_complete_graph_dot(&complete_graph, &options.start_valve, &routes)).unwrap();
+    }
 
     println!("best score: {}", best_score);
 }
 
+fn get_actor_names(actor_count: usize) -> Vec<String> {
+    match actor_count {
+        1 => vec!["You".to_string()],
+        2 => vec!["You".to_string(), "The elephant".to_string()],
+        _ => std::iter::once("You".to_string()).chain((1..actor_count).map(|i| format!("Elephant {}", i))).collect(),
+    }
+}
+
+struct Options {
+    // including me
+    actor_count: usize,
+    total_minutes: i32,
+    // time it takes to teach the helpers before anyone can start, if there are any
+    training_minutes: i32,
+    start_valve: String,
+    show_timeline: bool,
+    solver: Solver,
+    // also run the other solver and compare timings
+    benchmark: bool,
+    // Graphviz output for the tunnels as given and for the graph the solvers search
+    dot_path: Option<String>,
+    complete_dot_path: Option<String>,
+    // colour each actor's route in the Graphviz output
+    highlight_route: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Solver {
+    Recursive,
+    Bitmask,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        actor_count: 2,
+        total_minutes: 30,
+        training_minutes: 4,
+        start_valve: "AA".to_string(),
+        show_timeline: false,
+        solver: Solver::Bitmask,
+        benchmark: false,
+        dot_path: None,
+        complete_dot_path: None,
+        highlight_route: false,
+    };
+
+    for arg in std::env::args().skip(1) {
+        if let Some(s) = arg.strip_prefix("--actors=") {
+            options.actor_count = s.parse().unwrap();
+            assert!(options.actor_count > 0, "need at least one actor");
+        }
+        else if let Some(s) = arg.strip_prefix("--minutes=") {
+            options.total_minutes = s.parse().unwrap();
+        }
+        else if let Some(s) = arg.strip_prefix("--training=") {
+            options.training_minutes = s.parse().unwrap();
+        }
+        else if let Some(s) = arg.strip_prefix("--start=") {
+            options.start_valve = s.to_string();
+        }
+        else if arg == "--timeline" {
+            options.show_timeline = true;
+        }
+        else if let Some(s) = arg.strip_prefix("--solver=") {
+            options.solver = match s {
+                "recursive" => Solver::Recursive,
+                "bitmask" => Solver::Bitmask,
+                _ => panic!("unknown solver: {}", s),
+            };
+        }
+        else if arg == "--benchmark" {
+            options.benchmark = true;
+        }
+        else if let Some(s) = arg.strip_prefix("--dot=") {
+            options.dot_path = Some(s.to_string());
+        }
+        else if let Some(s) = arg.strip_prefix("--complete-dot=") {
+            options.complete_dot_path = Some(s.to_string());
+        }
+        else if arg == "--highlight" {
+            options.highlight_route = true;
+        }
+        else {
+            panic!("unknown argument: {}", arg);
+        }
+    }
+
+    options
+}
+
 #[derive(Debug)]
 struct Vertex {
     flow_rate: i32,
@@ -54,15 +190,19 @@ struct ActorState {
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 struct State {
-    me: ActorState,
-    elephant: ActorState,
-    activated_vertices: BTreeSet<VertexId>,
+    // kept sorted so that states that only differ by who is where are the same
+    actors: Vec<ActorState>,
+    activated_vertices: VertexSet,
 }
 
 #[derive(Debug)]
 struct Entry {
     state: State,
     score: i32,
+    // which actor in the parent state made the step, and where they went.
+    // Only needed to rebuild the plans, so kept cheap to make.
+    actor: usize,
+    moved_to: ActorState,
 }
 
 #[derive(Debug)]
@@ -74,11 +214,31 @@ struct WeightedVertex {
 #[derive(Debug)]
 struct WeightedGraph {
     vertices: HashMap<VertexId, WeightedVertex>,
+    // the valves worth opening and their flow rates, best first
+    valves_by_flow_rate: Vec<(VertexId, i32)>,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
 struct VertexId(i32);
 
+const VERTEX_SET_WORDS: usize = (26 * 26usize).div_ceil(64);
+
+/// One bit for every possible valve name, so it's cheap to copy and hash.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+struct VertexSet([u64; VERTEX_SET_WORDS]);
+
+impl VertexSet {
+    fn contains(&self, vertex: &VertexId) -> bool {
+        let i = usize::try_from(vertex.0).unwrap();
+        self.0[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn insert(&mut self, vertex: VertexId) {
+        let i = usize::try_from(vertex.0).unwrap();
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+}
+
 fn decode_vertex_name(name: &str) -> VertexId {
     let bytes = name.as_bytes();
     let first = i32::from(bytes[0] - b'A');
@@ -86,24 +246,18 @@ fn decode_vertex_name(name: &str) -> VertexId {
     VertexId((first * 26) + second)
 }
 
-fn get_neighbour_distances(graph: &Graph, vertex: VertexId) -> HashMap<VertexId, i32> {
-    let mut visited = HashMap::<VertexId, i32>::new();
-    let mut queue = VecDeque::<(i32, VertexId)>::new();
-
-    queue.push_back((0, vertex));
-    
-    while let Some((cost, vert)) = queue.pop_front() {
-        visited.insert(vert, cost);
-
-        for succ in &graph.vertices[&vert].neighbours {
-            if visited.contains_key(succ) {
-                continue;
-            }
-            queue.push_back((cost+1, *succ));
-        }
-    }
+fn encode_vertex_name(vertex: VertexId) -> String {
+    let first = u8::try_from(vertex.0 / 26).unwrap() + b'A';
+    let second = u8::try_from(vertex.0 % 26).unwrap() + b'A';
+    String::from_utf8(vec![first, second]).unwrap()
+}
 
-    visited
+fn get_neighbour_distances(graph: &Graph, vertex: VertexId) -> HashMap<VertexId, i32> {
+    bfs([vertex], |vert| graph.vertices[vert].neighbours.iter().cloned(), |_| false)
+        .distances
+        .into_iter()
+        .map(|(vert, cost)| (vert, cost as i32))
+        .collect()
 }
 
 fn get_complete_graph(graph: &Graph, initial_vertex: VertexId) -> WeightedGraph {
@@ -123,71 +277,57 @@ fn get_complete_graph(graph: &Graph, initial_vertex: VertexId) -> WeightedGraph
         (k, vertex)
     });
 
-    WeightedGraph { vertices: vertices.collect() }
+    let vertices = vertices.collect::<HashMap<_, _>>();
+    let mut valves_by_flow_rate = vertices.iter()
+        .filter(|(_, v)| v.flow_rate > 0)
+        .map(|(k, v)| (*k, v.flow_rate))
+        .collect::<Vec<_>>();
+    valves_by_flow_rate.sort_by_key(|&(k, flow_rate)| (-flow_rate, k));
+
+    WeightedGraph { vertices, valves_by_flow_rate }
 }
 
 fn get_successors_complete(graph: &WeightedGraph, state: &State) -> Vec<Entry> {
     let mut vec = Vec::new();
 
-    let me_current_vertex = &graph.vertices[&state.me.current_vertex];
-    let elephant_current_vertex = &graph.vertices[&state.elephant.current_vertex];
-
-    // new states if i move
-    for (neighbour, cost) in &me_current_vertex.neighbours {
-        if state.activated_vertices.contains(neighbour) {
+    for (actor_index, actor) in state.actors.iter().enumerate() {
+        // identical actors would just produce identical states
+        if actor_index > 0 && state.actors[actor_index - 1] == *actor {
             continue;
         }
-        let neighbour_vertex = &graph.vertices[neighbour];
 
-        let new_minutes_remaining = state.me.minutes_remaining - cost;
-        if new_minutes_remaining <= 0 {
-            continue;
-        }
-        let mut new_activated_vertices = state.activated_vertices.clone();
-        new_activated_vertices.insert(*neighbour);
-        vec.push(Entry {
-            score: neighbour_vertex.flow_rate * new_minutes_remaining,
-            state: State {
-                activated_vertices: new_activated_vertices,
-                me: ActorState {
-                    current_vertex: *neighbour,
-                    minutes_remaining: new_minutes_remaining
-                },
-                elephant: state.elephant.clone(),
+        let current_vertex = &graph.vertices[&actor.current_vertex];
+        for (neighbour, cost) in &current_vertex.neighbours {
+            if state.activated_vertices.contains(neighbour) {
+                continue;
             }
-        })
-    }
-
-    // new states if elephant moves
-    for (neighbour, cost) in &elephant_current_vertex.neighbours {
-        if state.activated_vertices.contains(neighbour) {
-            continue;
-        }
-        let neighbour_vertex = &graph.vertices[neighbour];
+            let neighbour_vertex = &graph.vertices[neighbour];
 
-        let new_minutes_remaining = state.elephant.minutes_remaining - cost;
-        if new_minutes_remaining <= 0 {
-            continue;
-        }
-        let mut new_activated_vertices = state.activated_vertices.clone();
-        new_activated_vertices.insert(*neighbour);
-        vec.push(Entry {
-            score: neighbour_vertex.flow_rate * new_minutes_remaining,
-            state: State {
-                activated_vertices: new_activated_vertices,
-                me: state.me.clone(),
-                elephant: ActorState {
-                    current_vertex: *neighbour,
-                    minutes_remaining: new_minutes_remaining,
-                },
+            let new_minutes_remaining = actor.minutes_remaining - cost;
+            if new_minutes_remaining <= 0 {
+                continue;
             }
-        })
-    }
-
-    // try to normalize the states by keeping the actors in order
-    for s in &mut vec {
-        if s.state.elephant < s.state.me {
-            swap(&mut s.state.me, &mut s.state.elephant);
+            let mut new_activated_vertices = state.activated_vertices;
+            new_activated_vertices.insert(*neighbour);
+
+            let moved_to = ActorState {
+                current_vertex: *neighbour,
+                minutes_remaining: new_minutes_remaining,
+            };
+            let mut new_actors = state.actors.clone();
+            new_actors[actor_index] = moved_to.clone();
+            // try to normalize the states by keeping the actors in order
+            new_actors.sort();
+
+            vec.push(Entry {
+                score: neighbour_vertex.flow_rate * new_minutes_remaining,
+                actor: actor_index,
+                moved_to,
+                state: State {
+                    activated_vertices: new_activated_vertices,
+                    actors: new_actors,
+                }
+            })
         }
     }
 
@@ -196,47 +336,70 @@ fn get_successors_complete(graph: &WeightedGraph, state: &State) -> Vec<Entry> {
 
 /// Always overestimates.
 fn get_remaining_potential_score_complete(graph: &WeightedGraph, state: &State) -> i32 {
-    let mut remaining_valve_scores = graph.vertices.iter()
-        .filter(|&(k, _)| !state.activated_vertices.contains(k))
-        .map(|(_, v)| v.flow_rate).collect::<Vec<_>>();
-    remaining_valve_scores.sort();
-    remaining_valve_scores.reverse();
+    let remaining_valve_scores = graph.valves_by_flow_rate.iter()
+        .filter(|(k, _)| !state.activated_vertices.contains(k))
+        .map(|(_, flow_rate)| *flow_rate);
 
     let mut sum = 0;
 
     // It will take at least 1 minute to do anything.
-    let mut me_remaining_time = state.me.minutes_remaining - 1;
-    let mut elephant_remaining_time = state.elephant.minutes_remaining - 1;
+    let mut remaining_times = state.actors.iter().map(|a| a.minutes_remaining - 1).collect::<Vec<_>>();
 
-    for score in remaining_valve_scores.into_iter() {
-        if me_remaining_time <= 0 || elephant_remaining_time <= 0 {
+    for score in remaining_valve_scores {
+        // give the next best valve to whoever has the most time left
+        let remaining_time = remaining_times.iter_mut().max().unwrap();
+        if *remaining_time <= 0 {
             break;
         }
-        if me_remaining_time > elephant_remaining_time {
-            sum += me_remaining_time * score;
-
-            // takes 2 minutes to move to another room from here and do something else
-            me_remaining_time -= 2;
-        }
-        else {
-            sum += elephant_remaining_time * score;
+        sum += *remaining_time * score;
 
-            // takes 2 minutes to move to another room from here and do something else
-            elephant_remaining_time -= 2;
-        }
+        // takes 2 minutes to move to another room from here and do something else
+        *remaining_time -= 2;
     }
 
     sum
 }
 
-fn find_best_score_rec_wrapper(graph: &WeightedGraph, initial_state: &State) -> i32 {
+/// Returns the best score along with the valves each actor should open to get it.
+fn find_best_plans(graph: &WeightedGraph, total_minutes: i32, initial_state: &State) -> (i32, Vec<Vec<PlanStep>>) {
     let mut lookup = HashMap::new();
-    find_best_score_rec(graph, initial_state, &mut lookup)
+    let best_score = find_best_score_rec(graph, initial_state, &mut lookup);
+
+    // Walk back down through the memoized scores, each time picking a successor that achieves the best.
+    // Ties are broken on the step itself rather than on hash map order, so the same input always gets the same plans.
+    let mut plans = vec![Vec::new(); initial_state.actors.len()];
+    // which actor is in each position of the normalized state
+    let mut slots = (0..initial_state.actors.len()).collect::<Vec<_>>();
+    let mut state = initial_state.clone();
+    let mut remaining_score = best_score;
+    while remaining_score > 0 {
+        let succ = get_successors_complete(graph, &state).into_iter()
+            .filter(|succ| succ.score + find_best_score_rec(graph, &succ.state, &mut lookup) == remaining_score)
+            .min_by_key(|succ| (-succ.moved_to.minutes_remaining, encode_vertex_name(succ.moved_to.current_vertex), succ.actor))
+            .unwrap();
+
+        remaining_score -= succ.score;
+        plans[slots[succ.actor]].push(PlanStep {
+            valve: encode_vertex_name(succ.moved_to.current_vertex),
+            minute: total_minutes - succ.moved_to.minutes_remaining,
+            pressure_released: succ.score,
+        });
+
+        // sort the actors the same way get_successors_complete did to keep track of who ended up where
+        let mut actors = state.actors.clone();
+        actors[succ.actor] = succ.moved_to;
+        let mut order = (0..actors.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| actors[a].cmp(&actors[b]));
+        slots = order.iter().map(|&i| slots[i]).collect();
+        state = succ.state;
+    }
+
+    (best_score, plans)
 }
 
 // manually memoized recursive func
 fn find_best_score_rec(graph: &WeightedGraph, initial_state: &State, lookup: &mut HashMap<State, i32>) -> i32 {
-    if let Some(score) = lookup.get(&initial_state) {
+    if let Some(score) = lookup.get(initial_state) {
         return *score;
     }
 
@@ -259,6 +422,357 @@ fn find_best_score_rec(graph: &WeightedGraph, initial_state: &State, lookup: &mu
     best_score
 }
 
+const MAX_BITMASK_VALVES: usize = 24;
+
+/// The valves worth opening, in a fixed order so that valve i can be bit i of a mask.
+fn get_valve_bits(graph: &WeightedGraph) -> Vec<VertexId> {
+    let mut valves = graph.vertices.iter()
+        .filter(|(_, v)| v.flow_rate > 0)
+        .map(|(k, _)| *k)
+        .collect::<Vec<_>>();
+    valves.sort();
+    // the solver keeps a table entry for every subset of valves
+    assert!(valves.len() <= MAX_BITMASK_VALVES, "{} valves is too many for the bitmask solver, try --solver=recursive", valves.len());
+    valves
+}
+
+/// A valve opened during get_best_per_mask's search, pointing back at the one opened before it.
+#[derive(Debug, Clone, Copy)]
+struct MaskStep {
+    previous: Option<usize>,
+    // index into the valve bits
+    valve: usize,
+    minute: i32,
+    pressure_released: i32,
+}
+
+struct BestPerMask {
+    // score and last step of the best plan for each set of valves, None if they can't all be opened in time
+    best: Vec<Option<(i32, Option<usize>)>>,
+    steps: Vec<MaskStep>,
+}
+
+impl BestPerMask {
+    fn score(&self, mask: usize) -> i32 {
+        self.best[mask].map_or(-1, |(s, _)| s)
+    }
+
+    fn plan(&self, mask: usize, valves: &[VertexId]) -> Vec<PlanStep> {
+        let mut plan = Vec::new();
+        let mut step = self.best[mask].unwrap().1;
+        while let Some(i) = step {
+            let s = self.steps[i];
+            plan.push(PlanStep {
+                valve: encode_vertex_name(valves[s.valve]),
+                minute: s.minute,
+                pressure_released: s.pressure_released,
+            });
+            step = s.previous;
+        }
+        plan.reverse();
+        plan
+    }
+}
+
+/// For every set of valves, the most pressure a single actor can release by opening exactly those valves,
+/// along with the plan that does it.
+fn get_best_per_mask(graph: &WeightedGraph, valves: &[VertexId], start: VertexId, total_minutes: i32) -> BestPerMask {
+    // costs[i][j] is the time to get from valve i to valve j and open it, the start is the last row
+    let costs = valves.iter().chain(std::iter::once(&start)).map(|from| {
+        let neighbours = &graph.vertices[from].neighbours;
+        valves.iter().map(|to| neighbours.iter().find(|(k, _)| k == to).unwrap().1).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    let mut best = vec![None; 1 << valves.len()];
+    best[0] = Some((0, None));
+    let mut steps = Vec::new();
+
+    let mut open_list = vec![(valves.len(), 0usize, total_minutes, 0, None)];
+    while let Some((current, mask, minutes_remaining, score, step)) = open_list.pop() {
+        for (next, cost) in costs[current].iter().enumerate() {
+            let bit = 1 << next;
+            if mask & bit != 0 {
+                continue;
+            }
+            let new_minutes_remaining = minutes_remaining - cost;
+            if new_minutes_remaining <= 0 {
+                continue;
+            }
+
+            let pressure_released = graph.vertices[&valves[next]].flow_rate * new_minutes_remaining;
+            let new_score = score + pressure_released;
+            steps.push(MaskStep {
+                previous: step,
+                valve: next,
+                minute: total_minutes - new_minutes_remaining,
+                pressure_released,
+            });
+            let new_step = Some(steps.len() - 1);
+
+            let new_mask = mask | bit;
+            let entry = &mut best[new_mask];
+            if entry.is_none_or(|(s, _)| new_score > s) {
+                *entry = Some((new_score, new_step));
+            }
+            open_list.push((next, new_mask, new_minutes_remaining, new_score, new_step));
+        }
+    }
+
+    BestPerMask { best, steps }
+}
+
+/// The best way to split the valves in mask between one more actor, who gets some subset of them,
+/// and everyone else, who share out the rest. Returns the total score and the subset.
+fn get_best_split(mask: usize, score_one: impl Fn(usize) -> i32, score_rest: impl Fn(usize) -> i32) -> (i32, usize) {
+    let mut best = (i32::MIN, 0);
+    // every subset of mask, from mask itself down to nothing
+    let mut subset = mask;
+    loop {
+        let total = score_one(subset) + score_rest(mask ^ subset);
+        if total > best.0 {
+            best = (total, subset);
+        }
+        if subset == 0 {
+            break;
+        }
+        subset = (subset - 1) & mask;
+    }
+    best
+}
+
+/// Same answer as find_best_plans, for any number of actors starting together.
+/// The actors never open the same valve, so the best team is the best single-actor plans for disjoint masks.
+fn find_best_plans_bitmask(graph: &WeightedGraph, start: VertexId, total_minutes: i32, actor_count: usize) -> (i32, Vec<Vec<PlanStep>>) {
+    let valves = get_valve_bits(graph);
+    let best_per_mask = get_best_per_mask(graph, &valves, start, total_minutes);
+    let best = &best_per_mask.best;
+    let score = |mask: usize| best_per_mask.score(mask);
+
+    // best_within[mask] is the mask of the best plan that only opens valves from mask
+    let mut best_within = (0..best.len()).collect::<Vec<_>>();
+    for bit in 0..valves.len() {
+        for mask in 0..best.len() {
+            if mask & (1 << bit) != 0 {
+                let candidate = best_within[mask ^ (1 << bit)];
+                if score(candidate) > score(best_within[mask]) {
+                    best_within[mask] = candidate;
+                }
+            }
+        }
+    }
+    let score_one = |mask: usize| score(best_within[mask]);
+
+    // splits[k][mask] is the best split of the valves in mask between k + 2 actors.
+    // Each of those walks every subset of every mask, so 3^valves work per actor past the second.
+    // The whole team only needs the full set, so the last actor is left out and done once below.
+    let mut splits: Vec<Vec<(i32, usize)>> = Vec::new();
+    for _ in 2..actor_count {
+        let score_rest = |mask: usize| splits.last().map_or_else(|| score_one(mask), |split| split[mask].0);
+        let split = (0..best.len()).map(|mask| get_best_split(mask, score_one, score_rest)).collect();
+        splits.push(split);
+    }
+
+    let full_mask = best.len() - 1;
+    let (total, masks) = if actor_count == 1 {
+        (score_one(full_mask), vec![full_mask])
+    } else {
+        let score_rest = |mask: usize| splits.last().map_or_else(|| score_one(mask), |split| split[mask].0);
+        let (total, subset) = get_best_split(full_mask, score_one, score_rest);
+
+        // follow the splits back down to see what everyone else got
+        let mut masks = vec![subset];
+        let mut rest = full_mask ^ subset;
+        for split in splits.iter().rev() {
+            let subset = split[rest].1;
+            masks.push(subset);
+            rest ^= subset;
+        }
+        masks.push(rest);
+        (total, masks)
+    };
+
+    (total, masks.into_iter().map(|mask| best_per_mask.plan(best_within[mask], &valves)).collect())
+}
+
+fn get_route(graph: &Graph, from: &str, to: &str) -> Vec<String> {
+    let to = decode_vertex_name(to);
+    bfs([decode_vertex_name(from)], |v| graph.vertices[v].neighbours.iter().cloned(), |v| *v == to)
+        .goal_path()
+        .unwrap()
+        .into_iter()
+        .map(encode_vertex_name)
+        .collect()
+}
+
+fn get_flow_rate(graph: &Graph, valve: &str) -> i32 {
+    graph.vertices[&decode_vertex_name(valve)].flow_rate
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlanStep {
+    valve: String,
+    // minute in which the valve gets opened, counting from 1
+    minute: i32,
+    // over the rest of the time
+    pressure_released: i32,
+}
+
+fn render_plan(actor: &str, plan: &[PlanStep]) -> String {
+    let mut s = format!("{}:\n", actor);
+    for step in plan {
+        s.push_str(&format!("  minute {:>2}: open valve {}, releasing {} pressure\n", step.minute, step.valve, step.pressure_released));
+    }
+    s
+}
+
+fn describe_open_valves(open_valves: &[(&str, i32)]) -> String {
+    let names = open_valves.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    let total = open_valves.iter().map(|(_, flow_rate)| flow_rate).sum::<i32>();
+    match names.len() {
+        0 => "No valves are open.".to_string(),
+        1 => format!("Valve {} is open, releasing {} pressure.", names[0], total),
+        2 => format!("Valves {} and {} are open, releasing {} pressure.", names[0], names[1], total),
+        n => format!("Valves {}, and {} are open, releasing {} pressure.", names[..n-1].join(", "), names[n-1], total),
+    }
+}
+
+fn conjugate(actor: &str, verb: &str) -> String {
+    if actor == "You" { verb.to_string() } else { format!("{}s", verb) }
+}
+
+/// Minute-by-minute walkthrough of the plans, in the same words as the puzzle.
+fn render_timeline(graph: &Graph, start: &str, total_minutes: i32, actors: &[(&str, &[PlanStep])]) -> String {
+    let minute_count = usize::try_from(total_minutes).unwrap();
+
+    // what each actor is doing in each minute
+    let mut actions = vec![vec![None; minute_count]; actors.len()];
+    for (i, (actor, plan)) in actors.iter().enumerate() {
+        let mut current = start.to_string();
+        let mut minute = 1;
+        for step in plan.iter() {
+            let route = get_route(graph, &current, &step.valve);
+            for hop in &route[1..] {
+                actions[i][minute - 1] = Some(format!("{} {} to valve {}.", actor, conjugate(actor, "move"), hop));
+                minute += 1;
+            }
+            assert_eq!(i32::try_from(minute).unwrap(), step.minute);
+            actions[i][minute - 1] = Some(format!("{} {} valve {}.", actor, conjugate(actor, "open"), step.valve));
+            minute += 1;
+            current = step.valve.clone();
+        }
+    }
+
+    let mut opened = actors.iter()
+        .flat_map(|(_, plan)| plan.iter())
+        .map(|step| (step.valve.as_str(), step.minute, get_flow_rate(graph, &step.valve)))
+        .collect::<Vec<_>>();
+    opened.sort();
+
+    let mut s = String::new();
+    for minute in 1..=total_minutes {
+        s.push_str(&format!("== Minute {} ==\n", minute));
+
+        let open_valves = opened.iter().filter(|(_, m, _)| *m < minute).map(|(name, _, flow_rate)| (*name, *flow_rate)).collect::<Vec<_>>();
+        s.push_str(&describe_open_valves(&open_valves));
+        s.push('\n');
+
+        for actor_actions in &actions {
+            if let Some(action) = &actor_actions[usize::try_from(minute).unwrap() - 1] {
+                s.push_str(action);
+                s.push('\n');
+            }
+        }
+        s.push('\n');
+    }
+    s
+}
+
+const ROUTE_COLOURS: [&str; 4] = ["red", "blue", "darkgreen", "orange"];
+
+// Edges are undirected, so always keep the endpoints in the same order.
+fn edge_key(a: &str, b: &str) -> (String, String) {
+    if a <= b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) }
+}
+
+/// Which actor walks along each edge, given how to get between two valves in the graph being drawn.
+fn get_route_edges<F: Fn(&str, &str) -> Vec<String>>(start: &str, routes: &[&[PlanStep]], get_hops: F) -> BTreeMap<(String, String), usize> {
+    let mut edges = BTreeMap::new();
+    for (actor, plan) in routes.iter().enumerate() {
+        let mut current = start;
+        for step in plan.iter() {
+            for hop in get_hops(current, &step.valve).windows(2) {
+                edges.insert(edge_key(&hop[0], &hop[1]), actor);
+            }
+            current = &step.valve;
+        }
+    }
+    edges
+}
+
+fn render_dot(start: &str, vertices: &[(String, i32)], edges: &BTreeMap<(String, String), Option<i32>>, route_edges: &BTreeMap<(String, String), usize>) -> String {
+    let mut s = "graph valves {\n".to_string();
+
+    for (name, flow_rate) in vertices {
+        let mut attrs = vec![format!("label=\"{}\\n{}\"", name, flow_rate)];
+        if name == start {
+            attrs.push("shape=doublecircle".to_string());
+        }
+        else if *flow_rate == 0 {
+            attrs.push("color=grey".to_string());
+        }
+        s += &format!("    {} [{}];\n", name, attrs.join(", "));
+    }
+
+    for (key, travel_time) in edges {
+        let mut attrs = Vec::new();
+        if let Some(t) = travel_time {
+            attrs.push(format!("label=\"{}\"", t));
+        }
+        if let Some(actor) = route_edges.get(key) {
+            attrs.push(format!("color={}", ROUTE_COLOURS[actor % ROUTE_COLOURS.len()]));
+            attrs.push("penwidth=3".to_string());
+        }
+
+        s += &format!("    {} -- {}", key.0, key.1);
+        if !attrs.is_empty() {
+            s += &format!(" [{}]", attrs.join(", "));
+        }
+        s += ";\n";
+    }
+
+    s += "}\n";
+    s
+}
+
+/// The tunnels as they appear in the input, with each actor's route through them drawn in their own colour.
+fn render_graph_dot(graph: &Graph, start: &str, routes: &[&[PlanStep]]) -> String {
+    let mut vertices = graph.vertices.iter().map(|(k, v)| (encode_vertex_name(*k), v.flow_rate)).collect::<Vec<_>>();
+    vertices.sort();
+
+    let edges = graph.vertices.iter()
+        .flat_map(|(k, v)| v.neighbours.iter().map(|n| (edge_key(&encode_vertex_name(*k), &encode_vertex_name(*n)), None)))
+        .collect();
+
+    let route_edges = get_route_edges(start, routes, |from, to| get_route(graph, from, to));
+    render_dot(start, &vertices, &edges, &route_edges)
+}
+
+/// The graph of valves worth opening that the solvers search, labelled with how long it takes to walk each edge.
+fn render_complete_graph_dot(graph: &WeightedGraph, start: &str, routes: &[&[PlanStep]]) -> String {
+    let mut vertices = graph.vertices.iter().map(|(k, v)| (encode_vertex_name(*k), v.flow_rate)).collect::<Vec<_>>();
+    vertices.sort();
+
+    // weights include the minute spent opening the valve at the end
+    let edges = graph.vertices.iter()
+        .flat_map(|(k, v)| v.neighbours.iter()
+            .filter(move |(n, _)| n != k)
+            .map(move |(n, cost)| (edge_key(&encode_vertex_name(*k), &encode_vertex_name(*n)), Some(cost - 1))))
+        .collect();
+
+    let route_edges = get_route_edges(start, routes, |from, to| vec![from.to_string(), to.to_string()]);
+    render_dot(start, &vertices, &edges, &route_edges)
+}
+
 fn parse_graph<T: Iterator<Item=GraphLine>>(lines: T) -> Graph {
     let mut m = HashMap::new();
     for line in lines {
@@ -283,4 +797,200 @@ fn parse_graph_line(line: &str) -> GraphLine {
         flow_rate: captures[2].parse().unwrap(),
         neighbours: captures[3].split(", ").map(|s| s.to_string()).collect(),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_best_plans_sample() {
+        let file = File::open("data/day16/sample_input.txt").unwrap();
+        let graph = parse_graph(BufReader::new(file).lines().map(|x| parse_graph_line(&x.unwrap())));
+        let complete_graph = get_complete_graph(&graph, decode_vertex_name("AA"));
+
+        let actor = ActorState { current_vertex: decode_vertex_name("AA"), minutes_remaining: 26 };
+        let (score, plans) = find_best_plans(&complete_graph, 26, &State {
+            activated_vertices: VertexSet::default(),
+            actors: vec![actor; 2],
+        });
+
+        assert_eq!(score, 1707);
+        assert_eq!(plans.iter().flatten().map(|s| s.pressure_released).sum::<i32>(), 1707);
+
+        let mut opened = plans.iter().flatten().map(|s| s.valve.as_str()).collect::<Vec<_>>();
+        opened.sort();
+        assert_eq!(opened, vec!["BB", "CC", "DD", "EE", "HH", "JJ"]);
+
+        let timeline = render_timeline(&graph, "AA", 26, &[("You", &plans[0]), ("The elephant", &plans[1])]);
+        assert!(timeline.ends_with("\
+== Minute 26 ==
+Valves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure.
+
+"));
+    }
+
+    #[test]
+    fn test_find_best_plans_bitmask_sample() {
+        let file = File::open("data/day16/sample_input.txt").unwrap();
+        let graph = parse_graph(BufReader::new(file).lines().map(|x| parse_graph_line(&x.unwrap())));
+        let start = decode_vertex_name("AA");
+        let complete_graph = get_complete_graph(&graph, start);
+
+        let (score, plans) = find_best_plans_bitmask(&complete_graph, start, 30, 1);
+        assert_eq!(score, 1651);
+        let plan = plans[0].iter().map(|s| (s.valve.as_str(), s.minute)).collect::<Vec<_>>();
+        assert_eq!(plan, vec![("DD", 2), ("BB", 5), ("JJ", 9), ("HH", 17), ("EE", 21), ("CC", 24)]);
+
+        for (actor_count, expected_score) in [(2, 1707), (3, 1794)] {
+            let (score, plans) = find_best_plans_bitmask(&complete_graph, start, 26, actor_count);
+            assert_eq!(score, expected_score);
+            assert_eq!(plans.len(), actor_count);
+            assert_eq!(plans.iter().flatten().map(|s| s.pressure_released).sum::<i32>(), expected_score);
+
+            // render_timeline checks that each plan can actually be walked in time
+            let names = get_actor_names(actor_count);
+            let actors = names.iter().map(|n| n.as_str()).zip(plans.iter().map(|p| p.as_slice())).collect::<Vec<_>>();
+            render_timeline(&graph, "AA", 26, &actors);
+        }
+    }
+
+    #[test]
+    fn test_solvers_agree() {
+        let file = File::open("data/day16/sample_input.txt").unwrap();
+        let graph = parse_graph(BufReader::new(file).lines().map(|x| parse_graph_line(&x.unwrap())));
+        let start = decode_vertex_name("AA");
+        let complete_graph = get_complete_graph(&graph, start);
+
+        for minutes in [5, 10, 20, 26] {
+            for actor_count in [1, 2, 3, 4] {
+                let actor = ActorState { current_vertex: start, minutes_remaining: minutes };
+                let (recursive, _) = find_best_plans(&complete_graph, minutes, &State {
+                    activated_vertices: VertexSet::default(),
+                    actors: vec![actor; actor_count],
+                });
+                let (bitmask, _) = find_best_plans_bitmask(&complete_graph, start, minutes, actor_count);
+                assert_eq!(recursive, bitmask, "{} minutes, {} actors", minutes, actor_count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_dot_sample() {
+        let file = File::open("data/day16/sample_input.txt").unwrap();
+        let graph = parse_graph(BufReader::new(file).lines().map(|x| parse_graph_line(&x.unwrap())));
+        let start = decode_vertex_name("AA");
+        let complete_graph = get_complete_graph(&graph, start);
+        let (_, plans) = find_best_plans_bitmask(&complete_graph, start, 30, 1);
+        let routes = [plans[0].as_slice()];
+
+        let dot = render_graph_dot(&graph, "AA", &[]);
+        assert!(dot.starts_with("graph valves {\n    AA [label=\"AA\\n0\", shape=doublecircle];\n    BB [label=\"BB\\n13\"];\n"));
+        assert!(dot.contains("    FF [label=\"FF\\n0\", color=grey];\n"));
+        assert!(dot.contains("    AA -- DD;\n"));
+        assert!(!dot.contains("DD -- AA"));
+        assert_eq!(dot.matches(" -- ").count(), 10);
+
+        // the route from AA walks down every tunnel at some point
+        let dot = render_graph_dot(&graph, "AA", &routes);
+        assert!(dot.contains("    AA -- DD [color=red, penwidth=3];\n"));
+        assert!(dot.contains("    II -- JJ [color=red, penwidth=3];\n"));
+        assert_eq!(dot.matches("color=red, penwidth=3").count(), 10);
+
+        let dot = render_complete_graph_dot(&complete_graph, "AA", &routes);
+        // 6 valves worth opening, all connected to each other and to the start
+        assert_eq!(dot.matches(" -- ").count(), 21);
+        assert!(dot.contains("    AA -- DD [label=\"1\", color=red, penwidth=3];\n"));
+        assert!(dot.contains("    BB -- DD [label=\"2\", color=red, penwidth=3];\n"));
+        assert!(dot.contains("    BB -- CC [label=\"1\"];\n"));
+        assert!(!dot.contains("FF"));
+    }
+
+    #[test]
+    fn test_encode_vertex_name() {
+        assert_eq!(encode_vertex_name(decode_vertex_name("AA")), "AA");
+        assert_eq!(encode_vertex_name(decode_vertex_name("ZQ")), "ZQ");
+    }
+
+    #[test]
+    fn test_find_best_plans_actor_counts() {
+        let file = File::open("data/day16/sample_input.txt").unwrap();
+        let graph = parse_graph(BufReader::new(file).lines().map(|x| parse_graph_line(&x.unwrap())));
+        let complete_graph = get_complete_graph(&graph, decode_vertex_name("AA"));
+
+        let actor = ActorState { current_vertex: decode_vertex_name("AA"), minutes_remaining: 30 };
+        let (score, plans) = find_best_plans(&complete_graph, 30, &State {
+            activated_vertices: VertexSet::default(),
+            actors: vec![actor],
+        });
+        assert_eq!(score, 1651);
+        assert_eq!(plans.len(), 1);
+
+        let actor = ActorState { current_vertex: decode_vertex_name("AA"), minutes_remaining: 26 };
+        for (actor_count, expected_score) in [(1, 1327), (2, 1707), (3, 1794)] {
+            let (score, plans) = find_best_plans(&complete_graph, 26, &State {
+                activated_vertices: VertexSet::default(),
+                actors: vec![actor.clone(); actor_count],
+            });
+            assert_eq!(score, expected_score);
+            assert_eq!(plans.len(), actor_count);
+            assert_eq!(plans.iter().flatten().map(|s| s.pressure_released).sum::<i32>(), score);
+        }
+
+        let (_, plans) = find_best_plans(&complete_graph, 26, &State {
+            activated_vertices: VertexSet::default(),
+            actors: vec![actor; 3],
+        });
+        let names = get_actor_names(3);
+        let actors = names.iter().map(|n| n.as_str()).zip(plans.iter().map(|p| p.as_slice())).collect::<Vec<_>>();
+        let timeline = render_timeline(&graph, "AA", 26, &actors);
+        assert!(timeline.starts_with("\
+== Minute 1 ==
+No valves are open.
+You move to valve BB.
+Elephant 1 moves to valve DD.
+Elephant 2 moves to valve II.
+
+== Minute 2 ==
+No valves are open.
+You open valve BB.
+Elephant 1 opens valve DD.
+Elephant 2 moves to valve JJ.
+
+== Minute 3 ==
+Valves BB and DD are open, releasing 33 pressure.
+You move to valve CC.
+Elephant 1 moves to valve EE.
+Elephant 2 opens valve JJ.
+
+== Minute 4 ==
+Valves BB, DD, and JJ are open, releasing 54 pressure.
+You open valve CC.
+Elephant 1 moves to valve FF.
+
+== Minute 5 ==
+Valves BB, CC, DD, and JJ are open, releasing 56 pressure.
+You move to valve DD.
+Elephant 1 moves to valve GG.
+
+== Minute 6 ==
+Valves BB, CC, DD, and JJ are open, releasing 56 pressure.
+You move to valve EE.
+Elephant 1 moves to valve HH.
+
+== Minute 7 ==
+Valves BB, CC, DD, and JJ are open, releasing 56 pressure.
+You open valve EE.
+Elephant 1 opens valve HH.
+
+== Minute 8 ==
+Valves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure.
+
+"));
+        assert!(timeline.ends_with("\
+== Minute 26 ==
+Valves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure.
+
+"));
+    }
+}
@@ -7,39 +7,181 @@ const ROCK_4: &str = "#\n#\n#\n#";
 const ROCK_5: &str = "##\n##";
 
 fn main() {
-    let file = File::open("data/day17/input.txt").unwrap();
-    let reader = BufReader::new(file);
-    let line = reader.lines().next().unwrap().unwrap();
-    let directions = line.bytes().map(parse_direction).collect::<Vec<_>>();
+    let options = parse_options();
+    let (directions, rocks) = load_puzzle(&options);
+
+    let mut state = create_initial_state(&directions, &rocks, options.rules);
+
+    let target_rocks_stopped = 2022;
+    let last_rock = target_rocks_stopped.max(options.dump_at.unwrap_or(0));
+
+    if options.show_jets {
+        print!("The first rock begins falling:\n{}\n", render_state(&state, options.render_rows, true));
+    }
 
-    let rocks = [ROCK_1, ROCK_2, ROCK_3, ROCK_4, ROCK_5].iter().map(|r| parse_rock(r)).collect::<Vec<_>>();
+    let mut height = None;
+    while state.rocks_stopped < last_rock {
+        let rocks_stopped = state.rocks_stopped;
+        if options.show_jets {
+            print!("{}", trace_step(&mut state, options.render_rows));
+        }
+        else {
+            step(&mut state);
+        }
 
-    let mut state = State {
-        grid: Grid::new(7, 6, false),
-        rocks: &rocks,
+        if state.rocks_stopped == rocks_stopped {
+            continue;
+        }
+        if options.render && !options.show_jets {
+            print!("After rock {}:\n{}\n", state.rocks_stopped, render_state(&state, options.render_rows, true));
+        }
+        if options.dump_at == Some(state.rocks_stopped) {
+            print!("After rock {}:\n{}\n", state.rocks_stopped, render_state(&state, None, true));
+        }
+        if state.rocks_stopped == target_rocks_stopped {
+            height = Some(state.tower_height);
+        }
+    }
+
+    println!("height: {}", height.unwrap());
+}
+
+fn create_initial_state<'a>(directions: &'a [Direction], rocks: &'a [Rock], rules: Rules) -> State<'a> {
+    State {
+        chamber: Chamber::new(rules.chamber_width, rules.spawn_gap + rocks[0].height()),
+        rocks,
+        rules,
         rock_index: 0,
-        pos: Position{x: 2, y: 3},
-        directions: &directions,
+        pos: spawn_position(&rules, 0),
+        directions,
         dir_index: 0,
         tower_height: 0,
         rocks_stopped: 0,
+    }
+}
+
+struct Options {
+    input_path: String,
+    // the puzzle's rocks if not given
+    rocks_path: Option<String>,
+    rules: Rules,
+    // draw the tower after every rock
+    render: bool,
+    // also draw every push and fall in between, like the puzzle does
+    show_jets: bool,
+    // how much of the top of the tower to draw, all of it if not given
+    render_rows: Option<usize>,
+    // draw the whole tower once this many rocks have stopped
+    dump_at: Option<usize>,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        input_path: "data/day17/input.txt".to_string(),
+        rocks_path: None,
+        rules: DEFAULT_RULES,
+        render: false,
+        show_jets: false,
+        render_rows: None,
+        dump_at: None,
     };
 
-    // print_state(&state);
+    for arg in std::env::args().skip(1) {
+        if let Some(s) = arg.strip_prefix("--input=") {
+            options.input_path = s.to_string();
+        }
+        else if let Some(s) = arg.strip_prefix("--rocks=") {
+            options.rocks_path = Some(s.to_string());
+        }
+        else if let Some(s) = arg.strip_prefix("--width=") {
+            options.rules.chamber_width = s.parse().unwrap();
+        }
+        else if let Some(s) = arg.strip_prefix("--spawn-x=") {
+            options.rules.spawn_x = s.parse().unwrap();
+        }
+        else if let Some(s) = arg.strip_prefix("--spawn-gap=") {
+            options.rules.spawn_gap = s.parse().unwrap();
+        }
+        else if arg == "--render" {
+            options.render = true;
+        }
+        else if arg == "--jets" {
+            options.show_jets = true;
+        }
+        else if let Some(s) = arg.strip_prefix("--rows=") {
+            options.render_rows = Some(s.parse().unwrap());
+        }
+        else if let Some(s) = arg.strip_prefix("--dump-at=") {
+            options.dump_at = Some(s.parse().unwrap());
+        }
+        else {
+            panic!("unknown argument: {}", arg);
+        }
+    }
+
+    options
+}
+
+/// Reads the jet pattern and the rocks, exiting with a message if the rocks are no good.
+fn load_puzzle(options: &Options) -> (Vec<Direction>, Vec<Rock>) {
+    let file = File::open(&options.input_path).unwrap();
+    let reader = BufReader::new(file);
+    let line = reader.lines().next().unwrap().unwrap();
+    let directions = line.bytes().map(parse_direction).collect::<Vec<_>>();
 
-    while state.rocks_stopped < 2022 {
-    // for i in 0..20 {
-        // println!("step {}", i);
-        step(&mut state);
-        // print_state(&state);
+    let rocks = match &options.rocks_path {
+        Some(path) => parse_rocks(&std::fs::read_to_string(path).unwrap()).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }),
+        None => create_initial_rocks(),
+    };
+    if let Err(e) = check_rules(&options.rules, &rocks) {
+        eprintln!("{}", e);
+        std::process::exit(1);
     }
 
-    println!("height: {}", state.tower_height);
+    (directions, rocks)
+}
+
+fn create_initial_rocks() -> Vec<Rock> {
+    [ROCK_1, ROCK_2, ROCK_3, ROCK_4, ROCK_5].iter().map(|r| parse_rock(r)).collect::<Vec<_>>()
+}
+
+/// Where new rocks appear and how much room they have.
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    chamber_width: usize,
+    // gap between the left wall and the left edge of a new rock
+    spawn_x: usize,
+    // empty rows between the top of the tower (or the floor) and the bottom of a new rock
+    spawn_gap: usize,
+}
+
+const DEFAULT_RULES: Rules = Rules { chamber_width: 7, spawn_x: 2, spawn_gap: 3 };
+
+fn spawn_position(rules: &Rules, tower_height: usize) -> Position {
+    Position{x: rules.spawn_x.try_into().unwrap(), y: (tower_height + rules.spawn_gap).try_into().unwrap()}
+}
+
+/// Every rock has to fit in the chamber from where it appears.
+fn check_rules(rules: &Rules, rocks: &[Rock]) -> Result<(), String> {
+    if rules.chamber_width == 0 || rules.chamber_width > 64 {
+        return Err(format!("chamber width {} is not between 1 and 64", rules.chamber_width));
+    }
+    if rocks.is_empty() {
+        return Err("no rocks".to_string());
+    }
+    match rocks.iter().position(|r| rules.spawn_x + r.width > rules.chamber_width) {
+        Some(i) => Err(format!("rock {} doesn't fit in the chamber when it appears {} from the left wall", i + 1, rules.spawn_x)),
+        None => Ok(()),
+    }
 }
 
 struct State<'a> {
-    grid: Grid<bool>,
-    rocks: &'a[Grid<bool>],
+    chamber: Chamber,
+    rocks: &'a[Rock],
+    rules: Rules,
     rock_index: usize,
     pos: Position,
     directions: &'a[Direction],
@@ -55,71 +197,125 @@ fn apply_dir(p: Position, dir: Direction) -> Position {
     }
 }
 
-fn collides(grid: &Grid<bool>, pos: &Position, rock: &Grid<bool>) -> bool {
-    if pos.x < 0 || pos.y < 0 || usize::try_from(pos.x).unwrap() + rock.width > grid.width || usize::try_from(pos.y).unwrap() + rock.height() > grid.height() {
+fn collides(chamber: &Chamber, pos: &Position, rock: &Rock) -> bool {
+    if pos.x < 0 || pos.y < 0 || usize::try_from(pos.x).unwrap() + rock.width > chamber.width || usize::try_from(pos.y).unwrap() + rock.height() > chamber.height() {
         return true
     }
 
-    rock.iter().map(|(p, v)| (p + *pos, v)).filter(|&(_, v)| *v).any(|(p, _)| *grid.get_pos(p))
+    let x = usize::try_from(pos.x).unwrap();
+    let y = usize::try_from(pos.y).unwrap();
+    rock.rows.iter().enumerate().any(|(dy, row)| chamber.rows[y + dy] & (row << x) != 0)
 }
 
-fn stamp_down(grid: &mut Grid<bool>, pos: &Position, rock: &Grid<bool>) {
-    for (p, v) in rock.iter().map(|(p, v)| (p + *pos, v)).filter(|&(_, v)| *v) {
-        grid.set_pos(p, *v);
+fn stamp_down(chamber: &mut Chamber, pos: &Position, rock: &Rock) {
+    let x = usize::try_from(pos.x).unwrap();
+    let y = usize::try_from(pos.y).unwrap();
+    for (dy, row) in rock.rows.iter().enumerate() {
+        chamber.rows[y + dy] |= row << x;
     }
 }
 
 fn step(state: &mut State) {
+    push(state);
+    fall(state);
+}
+
+/// Blows the falling rock sideways, returning which way and whether it actually moved.
+fn push(state: &mut State) -> (Direction, bool) {
     let rock = &state.rocks[state.rock_index];
+    let next_dir = state.directions[state.dir_index];
+    state.dir_index = (state.dir_index + 1) % state.directions.len();
+    let new_pos = apply_dir(state.pos, next_dir);
+    if collides(&state.chamber, &new_pos, rock) {
+        return (next_dir, false);
+    }
+    state.pos = new_pos;
+    (next_dir, true)
+}
 
-    // left movement
-    {
-        let next_dir = state.directions[state.dir_index];
-        state.dir_index = (state.dir_index + 1) % state.directions.len();
-        let new_pos = apply_dir(state.pos, next_dir);
-        if !collides(&state.grid, &new_pos, rock) {
-            state.pos = new_pos;
-        }
-    }
-
-    // down movement
-    {
-        let new_pos = Position{x:state.pos.x, y: state.pos.y-1};
-        if collides(&state.grid, &new_pos, rock) {
-            // set rock
-            stamp_down(&mut state.grid, &state.pos, rock);
-            state.rocks_stopped += 1;
-            state.tower_height = state.tower_height.max(usize::try_from(state.pos.y).unwrap() + rock.height());
-            // get a new rock
-            state.rock_index = (state.rock_index + 1) % state.rocks.len();
-            state.pos = Position{x: 2, y: (state.tower_height + 3).try_into().unwrap()};
-            state.grid.resize_height(usize::try_from(state.pos.y).unwrap() + state.rocks[state.rock_index].height(), false);
-        }
-        else {
-            state.pos = new_pos;
-        }
+/// Moves the falling rock down, returning true if it came to rest instead and the next rock appeared.
+fn fall(state: &mut State) -> bool {
+    let rock = &state.rocks[state.rock_index];
+    let new_pos = Position{x:state.pos.x, y: state.pos.y-1};
+    if !collides(&state.chamber, &new_pos, rock) {
+        state.pos = new_pos;
+        return false;
     }
+
+    // set rock
+    stamp_down(&mut state.chamber, &state.pos, rock);
+    state.rocks_stopped += 1;
+    state.tower_height = state.tower_height.max(usize::try_from(state.pos.y).unwrap() + rock.height());
+    // get a new rock
+    state.rock_index = (state.rock_index + 1) % state.rocks.len();
+    state.pos = spawn_position(&state.rules, state.tower_height);
+    state.chamber.resize_height(usize::try_from(state.pos.y).unwrap() + state.rocks[state.rock_index].height());
+    true
 }
 
-#[allow(dead_code)]
-fn print_state(state: &State) {
-    for y in (0..state.grid.height()).rev() {
-        for x in 0..state.grid.width {
-            let c =  { 
-                let pos_in_rock = Position{x:x.try_into().unwrap(), y:y.try_into().unwrap()} - state.pos;
-                let rock = &state.rocks[state.rock_index];
-                match rock.try_get_pos(pos_in_rock) {
-                    Some(true) => '@',
-                    Some(false) | None =>  {
-                        if *state.grid.get(x, y) { '#' } else { '.' }
-                    }
-                }
-            };
+/// Does a step, describing it the way the puzzle does.
+fn trace_step(state: &mut State, rows: Option<usize>) -> String {
+    let (dir, moved) = push(state);
+    let dir_name = match dir {
+        Direction::Left => "left",
+        Direction::Right => "right",
+    };
+    let mut s = if moved {
+        format!("Jet of gas pushes rock {}:\n", dir_name)
+    } else {
+        format!("Jet of gas pushes rock {}, but nothing happens:\n", dir_name)
+    };
+    s += &render_state(state, rows, true);
+    s += "\n";
+
+    if fall(state) {
+        s += "Rock falls 1 unit, causing it to come to rest:\n";
+        s += &render_state(state, rows, false);
+        s += "\nA new rock begins falling:\n";
+        s += &render_state(state, rows, true);
+    }
+    else {
+        s += "Rock falls 1 unit:\n";
+        s += &render_state(state, rows, true);
+    }
+    s += "\n";
+    s
+}
 
-            print!("{}", c);
+/// Draws the chamber like the puzzle does, from the top of the tower or falling rock down.
+/// Only the top rows are drawn if given, with ~ marking where it was cut off.
+fn render_state(state: &State, rows: Option<usize>, show_falling_rock: bool) -> String {
+    let rock = &state.rocks[state.rock_index];
+    let top = if show_falling_rock {
+        state.tower_height.max(usize::try_from(state.pos.y).unwrap() + rock.height())
+    } else {
+        state.tower_height
+    };
+    let bottom = rows.map_or(0, |r| top.saturating_sub(r));
+
+    let mut s = String::new();
+    for y in (bottom..top).rev() {
+        s.push('|');
+        for x in 0..state.chamber.width {
+            let pos_in_rock = Position{x:x.try_into().unwrap(), y:y.try_into().unwrap()} - state.pos;
+            let c = if show_falling_rock && rock.try_get_pos(pos_in_rock) == Some(true) {
+                '@'
+            } else if state.chamber.get(x, y) {
+                '#'
+            } else {
+                '.'
+            };
+            s.push(c);
         }
-        println!();
+        s += "|\n";
     }
+
+    let edge = if bottom == 0 { ('+', '-') } else { ('|', '~') };
+    s.push(edge.0);
+    s.extend(std::iter::repeat_n(edge.1, state.chamber.width));
+    s.push(edge.0);
+    s.push('\n');
+    s
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -136,22 +332,51 @@ fn parse_direction(dir: u8) -> Direction {
     }
 }
 
-fn parse_rock(s: &str) -> Grid<bool> {
+fn parse_rock_line(line: &str) -> Result<u64, String> {
+    if line.len() > 64 {
+        return Err("rock is wider than 64".to_string());
+    }
+    line.bytes().enumerate().try_fold(0, |row, (x, c)| match c {
+        b'#' => Ok(row | (1 << x)),
+        b'.' => Ok(row),
+        _ => Err(format!("invalid character '{}'", char::from(c))),
+    })
+}
+
+fn parse_rock(s: &str) -> Rock {
     let lines = s.lines().collect::<Vec<_>>();
-    let mut g = Grid::new(lines[0].len(), lines.len(), false);
-
-    for (y, l) in lines.iter().rev().enumerate() {
-        for (x, c) in l.bytes().enumerate() {
-            let val = match c {
-                b'#' => true,
-                b'.' => false,
-                _ => panic!("invalid char"),
-            };
-            g.set(x, y, val);
+    let width = lines.iter().map(|l| l.len()).max().unwrap();
+    let rows = lines.iter().rev().map(|l| parse_rock_line(l).unwrap()).collect();
+    Rock { width, rows }
+}
+
+/// Rocks drawn the same way as in the puzzle, separated by blank lines.
+fn parse_rocks(s: &str) -> Result<Vec<Rock>, String> {
+    let mut rocks = Vec::new();
+    let mut rows = Vec::new();
+    let mut width = 0;
+
+    for (i, line) in s.lines().chain(std::iter::once("")).enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !rows.is_empty() {
+                rows.reverse();
+                rocks.push(Rock { width, rows });
+                rows = Vec::new();
+                width = 0;
+            }
+            continue;
         }
+
+        let row = parse_rock_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        if row == 0 {
+            return Err(format!("line {}: empty row", i + 1));
+        }
+        rows.push(row);
+        width = width.max(line.len());
     }
 
-    g
+    Ok(rocks)
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
@@ -160,14 +385,6 @@ struct Position {
     y: i32,
 }
 
-impl std::ops::Add for Position {
-    type Output = Position;
-
-    fn add(self, rhs: Position) -> Position {
-        Position{x: self.x + rhs.x, y: self.y + rhs.y}
-    }
-}
-
 impl std::ops::Sub for Position {
     type Output = Position;
 
@@ -176,72 +393,148 @@ impl std::ops::Sub for Position {
     }
 }
 
-#[derive(Debug)]
-struct Grid<T: Clone> {
+/// The chamber as one bitmask per row, bottom row first, with bit x set if column x is filled.
+#[derive(Debug, Clone)]
+struct Chamber {
     width: usize,
-    vec: Vec<T>,
+    rows: Vec<u64>,
 }
 
-impl<T: Clone> Grid<T> {
-    fn new(width: usize, height: usize, val: T) -> Self {
-
-        Self { width: width, vec: vec![val; width*height] }
+impl Chamber {
+    fn new(width: usize, height: usize) -> Self {
+        assert!(width > 0 && width <= 64, "chamber must be between 1 and 64 columns wide");
+        Self { width, rows: vec![0; height] }
     }
 
     fn height(&self) -> usize {
-        self.vec.len() / self.width
+        self.rows.len()
     }
 
-    fn is_in_bounds(&self, x: usize, y: usize) -> bool {
-        x < self.width && y < self.height()
+    fn get(&self, x: usize, y: usize) -> bool {
+        assert!(x < self.width);
+        self.rows[y] & (1 << x) != 0
     }
 
-    fn pos_is_in_bounds(&self, pos: &Position) -> bool {
-        pos.x >= 0 && pos.y >= 0 && usize::try_from(pos.x).unwrap() < self.width && usize::try_from(pos.y).unwrap() < self.height()
+    fn resize_height(&mut self, rows: usize) {
+        self.rows.resize(rows, 0);
     }
+}
 
-    fn get(&self, x: usize, y: usize) -> &T {
-        assert!(self.is_in_bounds(x, y));
-        &self.vec[(y*self.width)+x]
-    }
+/// A rock in the same layout as the chamber, with its left edge at bit 0.
+#[derive(Debug, Clone)]
+struct Rock {
+    width: usize,
+    rows: Vec<u64>,
+}
 
-    fn get_pos(&self, pos: Position) -> &T {
-        self.get(pos.x.try_into().unwrap(), pos.y.try_into().unwrap())
+impl Rock {
+    fn height(&self) -> usize {
+        self.rows.len()
     }
 
-    #[allow(dead_code)]
-    fn try_get(&self, x: usize, y: usize) -> Option<&T> {
-        if self.is_in_bounds(x, y) {
-            Some(self.get(x, y))
-        }
-        else {
-            None
-        }
+    fn try_get_pos(&self, pos: Position) -> Option<bool> {
+        let x = usize::try_from(pos.x).ok().filter(|x| *x < self.width)?;
+        let row = usize::try_from(pos.y).ok().and_then(|y| self.rows.get(y))?;
+        Some(row & (1 << x) != 0)
     }
+}
 
-    fn try_get_pos(&self, pos: Position) -> Option<&T> {
-        if self.pos_is_in_bounds(&pos) {
-            Some(self.get_pos(pos))
-        }
-        else {
-            None
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn set(&mut self, x: usize, y: usize, val: T) {
-        assert!(self.is_in_bounds(x, y));
-        self.vec[(y*self.width)+x] = val;
-    }
+    #[test]
+    fn test_trace_first_rocks() {
+        let directions = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>".bytes().map(parse_direction).collect::<Vec<_>>();
+        let rocks = create_initial_rocks();
+        let mut state = create_initial_state(&directions, &rocks, DEFAULT_RULES);
 
-    fn set_pos(&mut self, pos: Position, val: T) {
-        self.set(usize::try_from(pos.x).unwrap(), usize::try_from(pos.y).unwrap(), val);
-    }
+        let mut trace = format!("The first rock begins falling:\n{}\n", render_state(&state, None, true));
+        while state.rocks_stopped < 1 {
+            trace += &trace_step(&mut state, None);
+        }
 
-    fn resize_height(&mut self, rows: usize, val: T) {
-        self.vec.resize(self.width * rows, val);
+        assert_eq!(trace, "\
+The first rock begins falling:
+|..@@@@.|
+|.......|
+|.......|
+|.......|
++-------+
+
+Jet of gas pushes rock right:
+|...@@@@|
+|.......|
+|.......|
+|.......|
++-------+
+
+Rock falls 1 unit:
+|...@@@@|
+|.......|
+|.......|
++-------+
+
+Jet of gas pushes rock right, but nothing happens:
+|...@@@@|
+|.......|
+|.......|
++-------+
+
+Rock falls 1 unit:
+|...@@@@|
+|.......|
++-------+
+
+Jet of gas pushes rock right, but nothing happens:
+|...@@@@|
+|.......|
++-------+
+
+Rock falls 1 unit:
+|...@@@@|
++-------+
+
+Jet of gas pushes rock left:
+|..@@@@.|
++-------+
+
+Rock falls 1 unit, causing it to come to rest:
+|..####.|
++-------+
+
+A new rock begins falling:
+|...@...|
+|..@@@..|
+|...@...|
+|.......|
+|.......|
+|.......|
+|..####.|
++-------+
+
+");
     }
 
-    fn iter(&self) -> impl Iterator<Item=(Position, &T)> {
-        (0..self.height()).flat_map(|y| (0..self.width).map(move |x| Position{x: x.try_into().unwrap(),y: y.try_into().unwrap()})).map(|p| (p, self.get_pos(p)))
+    #[test]
+    fn test_render_top_rows() {
+        let directions = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>".bytes().map(parse_direction).collect::<Vec<_>>();
+        let rocks = create_initial_rocks();
+        let mut state = create_initial_state(&directions, &rocks, DEFAULT_RULES);
+        while state.rocks_stopped < 10 {
+            step(&mut state);
+        }
+        assert_eq!(state.tower_height, 17);
+
+        // matches the end of the puzzle's picture after 10 rocks
+        assert_eq!(render_state(&state, Some(4), false), "\
+|....#..|
+|....#..|
+|....##.|
+|##..##.|
+|~~~~~~~|
+");
+        assert!(render_state(&state, None, false).ends_with("|..####.|\n+-------+\n"));
+        assert_eq!(render_state(&state, None, false).lines().count(), 18);
     }
-}
\ No newline at end of file
+}
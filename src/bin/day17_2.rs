@@ -1,4 +1,6 @@
-use std::{fs::File, io::{BufReader, BufRead}, collections::{VecDeque}};
+use std::{fs::File, io::{BufReader, BufRead}, collections::{VecDeque}, time::Instant};
+
+use day1::cycle::find_cycle;
 
 const ROCK_1: &str = "####";
 const ROCK_2: &str = ".#.\n###\n.#.";
@@ -7,52 +9,161 @@ const ROCK_4: &str = "#\n#\n#\n#";
 const ROCK_5: &str = "##\n##";
 
 fn main() {
-    let file = File::open("data/day17/input.txt").unwrap();
-    let reader = BufReader::new(file);
-    let line = reader.lines().next().unwrap().unwrap();
-    let directions = line.bytes().map(parse_direction).collect::<Vec<_>>();
-
-    let rocks = create_initial_rocks();
-    let mut state = create_initial_state(&directions, &rocks);
+    let options = parse_options();
+    let (directions, rocks) = load_puzzle(&options);
 
     let target_rocks_stopped = 1000000000000;
 
-    let warmup_rocks_count = 100000;
+    println!("height: {}", get_height_after(&directions, &rocks, options.rules, target_rocks_stopped));
 
-    // warm it up
-    for _ in 0..warmup_rocks_count {
-        drop_rock(&mut state);
+    if options.benchmark {
+        let benchmark_rocks = 1000000;
+        let start_time = Instant::now();
+        let mut state = create_initial_state(&directions, &rocks, options.rules);
+        for _ in 0..benchmark_rocks {
+            drop_rock(&mut state);
+        }
+        let elapsed = start_time.elapsed();
+
+        let height = state.tower_height + state.discarded_rows;
+        assert_eq!(height, get_height_after(&directions, &rocks, options.rules, benchmark_rocks));
+        println!("simulating {} rocks directly took {:?}, height: {}", benchmark_rocks, elapsed, height);
+
+        let cellwise_start_time = Instant::now();
+        let cellwise_height = drop_rocks_cellwise(&directions, &rocks, options.rules, benchmark_rocks);
+        let cellwise_elapsed = cellwise_start_time.elapsed();
+        assert_eq!(height, cellwise_height);
+        println!("simulating them a cell at a time took {:?}, speedup with bitmask rows: {:.2}x",
+            cellwise_elapsed, cellwise_elapsed.as_secs_f64() / elapsed.as_secs_f64());
     }
 
-    let initial_total_height = state.tower_height + state.discarded_rows;
-
-    let cycle = find_cycle(&state);
+    if let Some(count) = options.dump_at {
+        let mut state = create_initial_state(&directions, &rocks, options.rules);
+        for _ in 0..count {
+            drop_rock(&mut state);
+        }
+        print!("After rock {}:\n{}", count, render_state(&state, options.render_rows, true));
+    }
+}
 
-    let total_height_gained_in_cycle = cycle.iter().sum::<usize>();
-    let cycle_len = cycle.len();
+struct Options {
+    input_path: String,
+    // the puzzle's rocks if not given
+    rocks_path: Option<String>,
+    rules: Rules,
+    benchmark: bool,
+    // draw the top of the tower once this many rocks have stopped
+    dump_at: Option<usize>,
+    // how much of the top of the tower to draw, all of what's left if not given
+    render_rows: Option<usize>,
+}
 
-    let total_cycles = (target_rocks_stopped - warmup_rocks_count) / cycle_len;
+fn parse_options() -> Options {
+    let mut options = Options {
+        input_path: "data/day17/input.txt".to_string(),
+        rocks_path: None,
+        rules: DEFAULT_RULES,
+        benchmark: false,
+        dump_at: None,
+        render_rows: None,
+    };
+
+    for arg in std::env::args().skip(1) {
+        if let Some(s) = arg.strip_prefix("--input=") {
+            options.input_path = s.to_string();
+        }
+        else if let Some(s) = arg.strip_prefix("--rocks=") {
+            options.rocks_path = Some(s.to_string());
+        }
+        else if let Some(s) = arg.strip_prefix("--width=") {
+            options.rules.chamber_width = s.parse().unwrap();
+        }
+        else if let Some(s) = arg.strip_prefix("--spawn-x=") {
+            options.rules.spawn_x = s.parse().unwrap();
+        }
+        else if let Some(s) = arg.strip_prefix("--spawn-gap=") {
+            options.rules.spawn_gap = s.parse().unwrap();
+        }
+        else if arg == "--benchmark" {
+            options.benchmark = true;
+        }
+        else if let Some(s) = arg.strip_prefix("--dump-at=") {
+            options.dump_at = Some(s.parse().unwrap());
+        }
+        else if let Some(s) = arg.strip_prefix("--rows=") {
+            options.render_rows = Some(s.parse().unwrap());
+        }
+        else {
+            panic!("unknown argument: {}", arg);
+        }
+    }
 
-    let almost_final_height = initial_total_height + (total_cycles * total_height_gained_in_cycle);
-    let current_rocks_stopped = warmup_rocks_count + (total_cycles * cycle_len);
+    options
+}
 
-    let remaining_height = (current_rocks_stopped..target_rocks_stopped).zip(cycle.iter().cycle()).map(|x| *x.1).sum::<usize>();
+/// Reads the jet pattern and the rocks, exiting with a message if the rocks are no good.
+fn load_puzzle(options: &Options) -> (Vec<Direction>, Vec<Rock>) {
+    let file = File::open(&options.input_path).unwrap();
+    let reader = BufReader::new(file);
+    let line = reader.lines().next().unwrap().unwrap();
+    let directions = line.bytes().map(parse_direction).collect::<Vec<_>>();
 
-    let final_height = almost_final_height + remaining_height;
+    let rocks = match &options.rocks_path {
+        Some(path) => parse_rocks(&std::fs::read_to_string(path).unwrap()).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }),
+        None => create_initial_rocks(),
+    };
+    if let Err(e) = check_rules(&options.rules, &rocks) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 
-    println!("height: {}", final_height);
+    (directions, rocks)
 }
 
-fn create_initial_rocks() -> Vec<Grid<bool>> {
+fn create_initial_rocks() -> Vec<Rock> {
     [ROCK_1, ROCK_2, ROCK_3, ROCK_4, ROCK_5].iter().map(|r| parse_rock(r)).collect::<Vec<_>>()
 }
 
-fn create_initial_state<'a>(directions: &'a [Direction], rocks: &'a [Grid<bool>]) -> State<'a> {
+/// Where new rocks appear and how much room they have.
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    chamber_width: usize,
+    // gap between the left wall and the left edge of a new rock
+    spawn_x: usize,
+    // empty rows between the top of the tower (or the floor) and the bottom of a new rock
+    spawn_gap: usize,
+}
+
+const DEFAULT_RULES: Rules = Rules { chamber_width: 7, spawn_x: 2, spawn_gap: 3 };
+
+fn spawn_position(rules: &Rules, tower_height: usize) -> Position {
+    Position{x: rules.spawn_x.try_into().unwrap(), y: (tower_height + rules.spawn_gap).try_into().unwrap()}
+}
+
+/// Every rock has to fit in the chamber from where it appears.
+fn check_rules(rules: &Rules, rocks: &[Rock]) -> Result<(), String> {
+    if rules.chamber_width == 0 || rules.chamber_width > 64 {
+        return Err(format!("chamber width {} is not between 1 and 64", rules.chamber_width));
+    }
+    if rocks.is_empty() {
+        return Err("no rocks".to_string());
+    }
+    match rocks.iter().position(|r| rules.spawn_x + r.width > rules.chamber_width) {
+        Some(i) => Err(format!("rock {} doesn't fit in the chamber when it appears {} from the left wall", i + 1, rules.spawn_x)),
+        None => Ok(()),
+    }
+}
+
+fn create_initial_state<'a>(directions: &'a [Direction], rocks: &'a [Rock], rules: Rules) -> State<'a> {
     State {
-        grid: Grid::new(7, 6, false),
+        chamber: Chamber::new(rules.chamber_width, rules.spawn_gap + rocks[0].height()),
         rocks,
+        rules,
         rock_index: 0,
-        pos: Position{x: 2, y: 3},
+        pos: spawn_position(&rules, 0),
         directions,
         dir_index: 0,
         tower_height: 0,
@@ -61,45 +172,56 @@ fn create_initial_state<'a>(directions: &'a [Direction], rocks: &'a [Grid<bool>]
     }
 }
 
-fn create_delta_it(mut state: State) -> impl Iterator<Item=usize> + '_ {
-    let initial_total_height = state.tower_height + state.discarded_rows;
-    std::iter::repeat_with(move || {
-        drop_rock(&mut state);
-        state.tower_height + state.discarded_rows
-    }).scan(initial_total_height, |st, x| {
-        let delta = x - *st;
-        *st = x;
-        Some(delta)
-    })
+/// Everything that decides how the rest of the simulation plays out,
+/// as long as no rock can fall further down than the current surface.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct CycleKey {
+    rock_index: usize,
+    dir_index: usize,
+    // how far below the top of the tower the highest rock in each column is
+    surface: Vec<usize>,
 }
 
-// Brute force find the cycle in the height increases.
-// Pretty slow but it works.
-fn find_cycle(initial_state: &State) -> Vec<usize> {
-    for size in 1.. {
-        let mut it = create_delta_it(initial_state.clone());
-        let mut elems = Vec::new();
-        for _ in 0..size {
-            elems.push(it.next().unwrap());
-        }
-        if prove_cycle(&elems, &mut it) {
-            return elems;
-        }
+fn get_cycle_key(state: &State) -> CycleKey {
+    let surface = (0..state.chamber.width).map(|x| {
+        (0..state.tower_height).rev()
+            .find(|&y| state.chamber.get(x, y))
+            .map_or(state.tower_height, |y| state.tower_height - 1 - y)
+    }).collect();
+
+    CycleKey {
+        rock_index: state.rock_index,
+        dir_index: state.dir_index,
+        surface,
     }
-
-    panic!("should never get here")
 }
 
-// "proves" that the given iterator is a cycle of the given elems
-// by iterating it a lot of times until we are convinced enough
-fn prove_cycle<T: Iterator<Item=usize>>(elems: &[usize], it: &mut T) -> bool {
-    it.zip(elems.iter().cycle().take(elems.len() * 1000)).all(|(x, y)| x == *y)
+/// Drops rocks until the state repeats, then skips ahead whole cycles to work out the height after count rocks.
+fn get_height_after(directions: &[Direction], rocks: &[Rock], rules: Rules, count: usize) -> usize {
+    let mut state = create_initial_state(directions, rocks, rules);
+
+    // heights[i] is the total height after i rocks have stopped, matching the keys
+    let mut heights = vec![0];
+    let keys = std::iter::once(get_cycle_key(&state)).chain(std::iter::repeat_with(|| {
+        drop_rock(&mut state);
+        heights.push(state.tower_height + state.discarded_rows);
+        get_cycle_key(&state)
+    }));
+
+    match find_cycle(keys.take(count + 1)) {
+        None => heights[count],
+        Some(cycle) => {
+            let height_gained_in_cycle = heights[cycle.start + cycle.length] - heights[cycle.start];
+            heights[cycle.fold_index(count)] + (cycle.laps_before(count) * height_gained_in_cycle)
+        }
+    }
 }
 
 #[derive(Clone)]
 struct State<'a> {
-    grid: Grid<bool>,
-    rocks: &'a[Grid<bool>],
+    chamber: Chamber,
+    rocks: &'a[Rock],
+    rules: Rules,
     rock_index: usize,
     pos: Position,
     directions: &'a[Direction],
@@ -116,27 +238,28 @@ fn apply_dir(p: Position, dir: Direction) -> Position {
     }
 }
 
-fn collides(grid: &Grid<bool>, pos: &Position, rock: &Grid<bool>) -> bool {
-    if pos.x < 0 || pos.y < 0 || usize::try_from(pos.x).unwrap() + rock.width > grid.width || usize::try_from(pos.y).unwrap() + rock.height() > grid.height() {
+fn collides(chamber: &Chamber, pos: &Position, rock: &Rock) -> bool {
+    if pos.x < 0 || pos.y < 0 || usize::try_from(pos.x).unwrap() + rock.width > chamber.width || usize::try_from(pos.y).unwrap() + rock.height() > chamber.height() {
         return true
     }
 
-    rock.iter().map(|(p, v)| (p + *pos, v)).filter(|&(_, v)| *v).any(|(p, _)| *grid.get_pos(p))
+    let x = usize::try_from(pos.x).unwrap();
+    let y = usize::try_from(pos.y).unwrap();
+    rock.rows.iter().enumerate().any(|(dy, row)| chamber.rows[y + dy] & (row << x) != 0)
 }
 
-fn stamp_down(grid: &mut Grid<bool>, pos: &Position, rock: &Grid<bool>) {
-    for (p, v) in rock.iter().map(|(p, v)| (p + *pos, v)).filter(|&(_, v)| *v) {
-        grid.set_pos(p, *v);
+fn stamp_down(chamber: &mut Chamber, pos: &Position, rock: &Rock) {
+    let x = usize::try_from(pos.x).unwrap();
+    let y = usize::try_from(pos.y).unwrap();
+    for (dy, row) in rock.rows.iter().enumerate() {
+        chamber.rows[y + dy] |= row << x;
     }
 }
 
 /// really bad name, but returns true if blocks can't get through this row + prev row combined.
-fn is_row_and_or_below_full(grid: &Grid<bool>, row: usize) -> bool {
-    (0..grid.width).map(|x| {
-        let v = *grid.get(x, row);
-        let u = if row > 0 { *grid.get(x, row - 1) } else { true };
-        v || u
-    }).all(|v| v)
+fn is_row_and_or_below_full(chamber: &Chamber, row: usize) -> bool {
+    let below = if row > 0 { chamber.rows[row - 1] } else { chamber.full_row() };
+    (chamber.rows[row] | below) == chamber.full_row()
 }
 
 fn drop_rock(state: &mut State) {
@@ -154,7 +277,7 @@ fn step(state: &mut State) {
         let next_dir = state.directions[state.dir_index];
         state.dir_index = (state.dir_index + 1) % state.directions.len();
         let new_pos = apply_dir(state.pos, next_dir);
-        if !collides(&state.grid, &new_pos, rock) {
+        if !collides(&state.chamber, &new_pos, rock) {
             state.pos = new_pos;
         }
     }
@@ -162,28 +285,28 @@ fn step(state: &mut State) {
     // down movement
     {
         let new_pos = Position{x:state.pos.x, y: state.pos.y-1};
-        if collides(&state.grid, &new_pos, rock) {
+        if collides(&state.chamber, &new_pos, rock) {
             // set rock
-            stamp_down(&mut state.grid, &state.pos, rock);
+            stamp_down(&mut state.chamber, &state.pos, rock);
             state.rocks_stopped += 1;
             state.tower_height = state.tower_height.max(usize::try_from(state.pos.y).unwrap() + rock.height());
 
-            // trim the grid down
+            // trim the chamber down
             let trimmable_row_index = (0..rock.height())
                 .rev()
                 .map(|dy| usize::try_from(state.pos.y).unwrap() + dy)
-                .find(|y| is_row_and_or_below_full(&state.grid, *y));
+                .find(|y| is_row_and_or_below_full(&state.chamber, *y));
             if let Some(y) = trimmable_row_index {
                 // We want to keep row y and drop all the rows below it.
-                state.grid.drop_rows_from_start(y);
+                state.chamber.drop_rows_from_start(y);
                 state.tower_height -= y;
                 state.discarded_rows += y;
             }
 
             // get a new rock
             state.rock_index = (state.rock_index + 1) % state.rocks.len();
-            state.pos = Position{x: 2, y: (state.tower_height + 3).try_into().unwrap()};
-            state.grid.resize_height(usize::try_from(state.pos.y).unwrap() + state.rocks[state.rock_index].height(), false);
+            state.pos = spawn_position(&state.rules, state.tower_height);
+            state.chamber.resize_height(usize::try_from(state.pos.y).unwrap() + state.rocks[state.rock_index].height());
         }
         else {
             state.pos = new_pos;
@@ -191,25 +314,92 @@ fn step(state: &mut State) {
     }
 }
 
-#[allow(dead_code)]
-fn print_state(state: &State) {
-    for y in (0..state.grid.height()).rev() {
-        for x in 0..state.grid.width {
-            let c =  { 
-                let pos_in_rock = Position{x:x.try_into().unwrap(), y:y.try_into().unwrap()} - state.pos;
-                let rock = &state.rocks[state.rock_index];
-                match rock.try_get_pos(pos_in_rock) {
-                    Some(true) => '@',
-                    Some(false) | None =>  {
-                        if *state.grid.get(x, y) { '#' } else { '.' }
-                    }
-                }
+/// The straightforward way of simulating, for --benchmark to compare against:
+/// the chamber is a grid of cells and rocks are lists of cells, checked one at a time, and nothing is ever thrown away.
+/// Returns the tower height once count rocks have stopped.
+fn drop_rocks_cellwise(directions: &[Direction], rocks: &[Rock], rules: Rules, count: usize) -> usize {
+    let rock_cells = rocks.iter().map(|rock| {
+        (0..rock.height()).flat_map(|y| (0..rock.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| rock.rows[y] & (1 << x) != 0)
+            .collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    let mut cells: Vec<Vec<bool>> = Vec::new();
+    let collides = |cells: &Vec<Vec<bool>>, rock: &[(usize, usize)], x: i64, y: i64| {
+        rock.iter().any(|&(dx, dy)| {
+            let (cx, cy) = (x + dx as i64, y + dy as i64);
+            cx < 0 || cy < 0 || cx >= rules.chamber_width as i64
+                || cells.get(cy as usize).is_some_and(|row| row[cx as usize])
+        })
+    };
+
+    let mut tower_height = 0;
+    let mut dir_index = 0;
+    for i in 0..count {
+        let rock = &rock_cells[i % rocks.len()];
+        let (mut x, mut y) = (rules.spawn_x as i64, (tower_height + rules.spawn_gap) as i64);
+        loop {
+            let dx = match directions[dir_index] {
+                Direction::Left => -1,
+                Direction::Right => 1,
             };
+            dir_index = (dir_index + 1) % directions.len();
+            if !collides(&cells, rock, x + dx, y) {
+                x += dx;
+            }
+            if collides(&cells, rock, x, y - 1) {
+                break;
+            }
+            y -= 1;
+        }
+
+        for &(dx, dy) in rock {
+            let (cx, cy) = ((x as usize) + dx, (y as usize) + dy);
+            if cells.len() <= cy {
+                cells.resize(cy + 1, vec![false; rules.chamber_width]);
+            }
+            cells[cy][cx] = true;
+        }
+        tower_height = cells.len();
+    }
+    tower_height
+}
 
-            print!("{}", c);
+/// Draws the chamber like the puzzle does, from the top of the tower or falling rock down.
+/// Only the top rows are drawn if given, with ~ marking where it was cut off,
+/// which is always the case once rows at the bottom have been thrown away.
+fn render_state(state: &State, rows: Option<usize>, show_falling_rock: bool) -> String {
+    let rock = &state.rocks[state.rock_index];
+    let top = if show_falling_rock {
+        state.tower_height.max(usize::try_from(state.pos.y).unwrap() + rock.height())
+    } else {
+        state.tower_height
+    };
+    let bottom = rows.map_or(0, |r| top.saturating_sub(r));
+
+    let mut s = String::new();
+    for y in (bottom..top).rev() {
+        s.push('|');
+        for x in 0..state.chamber.width {
+            let pos_in_rock = Position{x:x.try_into().unwrap(), y:y.try_into().unwrap()} - state.pos;
+            let c = if show_falling_rock && rock.try_get_pos(pos_in_rock) == Some(true) {
+                '@'
+            } else if state.chamber.get(x, y) {
+                '#'
+            } else {
+                '.'
+            };
+            s.push(c);
         }
-        println!();
+        s += "|\n";
     }
+
+    let edge = if bottom == 0 && state.discarded_rows == 0 { ('+', '-') } else { ('|', '~') };
+    s.push(edge.0);
+    s.extend(std::iter::repeat_n(edge.1, state.chamber.width));
+    s.push(edge.0);
+    s.push('\n');
+    s
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -226,22 +416,51 @@ fn parse_direction(dir: u8) -> Direction {
     }
 }
 
-fn parse_rock(s: &str) -> Grid<bool> {
+fn parse_rock_line(line: &str) -> Result<u64, String> {
+    if line.len() > 64 {
+        return Err("rock is wider than 64".to_string());
+    }
+    line.bytes().enumerate().try_fold(0, |row, (x, c)| match c {
+        b'#' => Ok(row | (1 << x)),
+        b'.' => Ok(row),
+        _ => Err(format!("invalid character '{}'", char::from(c))),
+    })
+}
+
+fn parse_rock(s: &str) -> Rock {
     let lines = s.lines().collect::<Vec<_>>();
-    let mut g = Grid::new(lines[0].len(), lines.len(), false);
-
-    for (y, l) in lines.iter().rev().enumerate() {
-        for (x, c) in l.bytes().enumerate() {
-            let val = match c {
-                b'#' => true,
-                b'.' => false,
-                _ => panic!("invalid char"),
-            };
-            g.set(x, y, val);
+    let width = lines.iter().map(|l| l.len()).max().unwrap();
+    let rows = lines.iter().rev().map(|l| parse_rock_line(l).unwrap()).collect();
+    Rock { width, rows }
+}
+
+/// Rocks drawn the same way as in the puzzle, separated by blank lines.
+fn parse_rocks(s: &str) -> Result<Vec<Rock>, String> {
+    let mut rocks = Vec::new();
+    let mut rows = Vec::new();
+    let mut width = 0;
+
+    for (i, line) in s.lines().chain(std::iter::once("")).enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !rows.is_empty() {
+                rows.reverse();
+                rocks.push(Rock { width, rows });
+                rows = Vec::new();
+                width = 0;
+            }
+            continue;
         }
+
+        let row = parse_rock_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        if row == 0 {
+            return Err(format!("line {}: empty row", i + 1));
+        }
+        rows.push(row);
+        width = width.max(line.len());
     }
 
-    g
+    Ok(rocks)
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
@@ -250,14 +469,6 @@ struct Position {
     y: i64,
 }
 
-impl std::ops::Add for Position {
-    type Output = Position;
-
-    fn add(self, rhs: Position) -> Position {
-        Position{x: self.x + rhs.x, y: self.y + rhs.y}
-    }
-}
-
 impl std::ops::Sub for Position {
     type Output = Position;
 
@@ -266,95 +477,179 @@ impl std::ops::Sub for Position {
     }
 }
 
+/// The chamber as one bitmask per row, bottom row first, with bit x set if column x is filled.
 #[derive(Debug, Clone)]
-struct Grid<T: Clone> {
+struct Chamber {
     width: usize,
-    vec: VecDeque<T>,
+    rows: VecDeque<u64>,
 }
 
-impl<T: Clone> Grid<T> {
-    fn new(width: usize, height: usize, val: T) -> Self {
-        let v = VecDeque::from_iter(std::iter::repeat(val).take(width*height));
-        Self { width: width, vec: v }
+impl Chamber {
+    fn new(width: usize, height: usize) -> Self {
+        assert!(width > 0 && width <= 64, "chamber must be between 1 and 64 columns wide");
+        Self { width, rows: VecDeque::from(vec![0; height]) }
     }
 
     fn height(&self) -> usize {
-        self.vec.len() / self.width
+        self.rows.len()
     }
 
-    fn is_in_bounds(&self, x: usize, y: usize) -> bool {
-        x < self.width && y < self.height()
+    fn full_row(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
     }
 
-    fn pos_is_in_bounds(&self, pos: &Position) -> bool {
-        pos.x >= 0 && pos.y >= 0 && usize::try_from(pos.x).unwrap() < self.width && usize::try_from(pos.y).unwrap() < self.height()
+    fn get(&self, x: usize, y: usize) -> bool {
+        assert!(x < self.width);
+        self.rows[y] & (1 << x) != 0
     }
 
-    fn get(&self, x: usize, y: usize) -> &T {
-        assert!(self.is_in_bounds(x, y));
-        &self.vec[(y*self.width)+x]
+    fn resize_height(&mut self, rows: usize) {
+        self.rows.resize(rows, 0);
     }
 
-    fn get_pos(&self, pos: Position) -> &T {
-        self.get(pos.x.try_into().unwrap(), pos.y.try_into().unwrap())
+    fn drop_rows_from_start(&mut self, rows: usize) {
+        self.rows.drain(0..rows);
     }
+}
 
-    #[allow(dead_code)]
-    fn try_get(&self, x: usize, y: usize) -> Option<&T> {
-        if self.is_in_bounds(x, y) {
-            Some(self.get(x, y))
-        }
-        else {
-            None
-        }
+/// A rock in the same layout as the chamber, with its left edge at bit 0.
+#[derive(Debug, Clone)]
+struct Rock {
+    width: usize,
+    rows: Vec<u64>,
+}
+
+impl Rock {
+    fn height(&self) -> usize {
+        self.rows.len()
     }
 
-    fn try_get_pos(&self, pos: Position) -> Option<&T> {
-        if self.pos_is_in_bounds(&pos) {
-            Some(self.get_pos(pos))
-        }
-        else {
-            None
+    fn try_get_pos(&self, pos: Position) -> Option<bool> {
+        let x = usize::try_from(pos.x).ok().filter(|x| *x < self.width)?;
+        let row = usize::try_from(pos.y).ok().and_then(|y| self.rows.get(y))?;
+        Some(row & (1 << x) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_rock() {
+        let directions_str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+        let directions = directions_str.bytes().map(parse_direction).collect::<Vec<_>>();
+        let rocks = create_initial_rocks();
+        let mut state = create_initial_state(&directions, &rocks, DEFAULT_RULES);
+        for _ in 0..2022 {
+            drop_rock(&mut state);
         }
+        assert_eq!(state.tower_height + state.discarded_rows, 3068);
+        assert_eq!(drop_rocks_cellwise(&directions, &rocks, DEFAULT_RULES, 2022), 3068);
     }
 
-    fn set(&mut self, x: usize, y: usize, val: T) {
-        assert!(self.is_in_bounds(x, y));
-        self.vec[(y*self.width)+x] = val;
+    #[test]
+    fn test_render_state() {
+        let directions_str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+        let directions = directions_str.bytes().map(parse_direction).collect::<Vec<_>>();
+        let rocks = create_initial_rocks();
+        let mut state = create_initial_state(&directions, &rocks, DEFAULT_RULES);
+        assert_eq!(render_state(&state, None, true), "|..@@@@.|\n|.......|\n|.......|\n|.......|\n+-------+\n");
+
+        for _ in 0..2022 {
+            drop_rock(&mut state);
+        }
+        // the bottom has been trimmed off by now
+        assert!(state.discarded_rows > 0);
+        let picture = render_state(&state, None, false);
+        assert_eq!(picture.lines().count(), state.tower_height + 1);
+        assert!(picture.ends_with("|~~~~~~~|\n"));
+        assert_eq!(render_state(&state, Some(3), true).lines().count(), 4);
     }
 
-    fn set_pos(&mut self, pos: Position, val: T) {
-        self.set(usize::try_from(pos.x).unwrap(), usize::try_from(pos.y).unwrap(), val);
+    #[test]
+    fn test_parse_rock() {
+        let rock = parse_rock(ROCK_3);
+        assert_eq!(rock.width, 3);
+        assert_eq!(rock.rows, vec![0b111, 0b100, 0b100]);
     }
 
-    fn resize_height(&mut self, rows: usize, val: T) {
-        self.vec.resize(self.width * rows, val);
+    #[test]
+    fn test_parse_rocks() {
+        let rocks = parse_rocks(&std::fs::read_to_string("data/day17/rocks.txt").unwrap()).unwrap();
+        let expected = create_initial_rocks();
+        assert_eq!(rocks.len(), expected.len());
+        for (rock, expected) in rocks.iter().zip(expected.iter()) {
+            assert_eq!(rock.width, expected.width);
+            assert_eq!(rock.rows, expected.rows);
+        }
+
+        assert_eq!(parse_rocks("##\n\n\n#.\n##\n").unwrap().len(), 2);
+        assert_eq!(parse_rocks("##\n\n#x\n").unwrap_err(), "line 3: invalid character 'x'");
+        assert_eq!(parse_rocks("#\n..\n").unwrap_err(), "line 2: empty row");
     }
-    
-    fn drop_rows_from_start(&mut self, rows: usize) {
-        let new_start_idx = self.width * rows;
-        self.vec.drain(0..new_start_idx);
 
+    #[test]
+    fn test_check_rules() {
+        let rocks = create_initial_rocks();
+        assert!(check_rules(&DEFAULT_RULES, &rocks).is_ok());
+        assert!(check_rules(&Rules { chamber_width: 65, ..DEFAULT_RULES }, &rocks).is_err());
+        assert_eq!(check_rules(&Rules { spawn_x: 4, ..DEFAULT_RULES }, &rocks).unwrap_err(),
+            "rock 1 doesn't fit in the chamber when it appears 4 from the left wall");
     }
 
-    fn iter(&self) -> impl Iterator<Item=(Position, &T)> {
-        (0..self.height()).flat_map(|y| (0..self.width).map(move |x| Position{x: x.try_into().unwrap(),y: y.try_into().unwrap()})).map(|p| (p, self.get_pos(p)))
+    #[test]
+    fn test_custom_rules() {
+        // single cells in a one wide chamber just stack up
+        let rocks = vec![parse_rock("#")];
+        let rules = Rules { chamber_width: 1, spawn_x: 0, spawn_gap: 5 };
+        let directions = [Direction::Left, Direction::Right];
+        assert_eq!(get_height_after(&directions, &rocks, rules, 10), 10);
+        assert_eq!(get_height_after(&directions, &rocks, rules, 1000000000000), 1000000000000);
+
+        // a wider chamber and further drop makes a difference on the sample
+        let directions = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>".bytes().map(parse_direction).collect::<Vec<_>>();
+        let rocks = create_initial_rocks();
+        let rules = Rules { chamber_width: 9, spawn_x: 3, spawn_gap: 4 };
+        let mut state = create_initial_state(&directions, &rocks, rules);
+        for _ in 0..2022 {
+            drop_rock(&mut state);
+        }
+        assert_eq!(get_height_after(&directions, &rocks, rules, 2022), state.tower_height + state.discarded_rows);
+        assert_ne!(get_height_after(&directions, &rocks, rules, 2022), 3068);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_collides_wide_chamber() {
+        let rock = parse_rock(ROCK_2);
+        let mut chamber = Chamber::new(64, 4);
+        assert!(!collides(&chamber, &Position{x: 61, y: 0}, &rock));
+        assert!(collides(&chamber, &Position{x: 62, y: 0}, &rock));
+
+        stamp_down(&mut chamber, &Position{x: 61, y: 0}, &rock);
+        assert_eq!(chamber.rows[1], 0b111 << 61);
+        assert!(chamber.get(63, 1));
+        assert!(collides(&chamber, &Position{x: 59, y: 0}, &rock));
+        assert!(!collides(&chamber, &Position{x: 58, y: 0}, &rock));
+        assert!(!is_row_and_or_below_full(&chamber, 2));
+        assert_eq!(Chamber::new(64, 1).full_row(), u64::MAX);
+    }
 
     #[test]
-    fn test_drop_rock() {
+    fn test_get_height_after() {
         let directions_str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
         let directions = directions_str.bytes().map(parse_direction).collect::<Vec<_>>();
         let rocks = create_initial_rocks();
-        let mut state = create_initial_state(&directions, &rocks);
-        for _ in 0..2022 {
+        assert_eq!(get_height_after(&directions, &rocks, DEFAULT_RULES, 0), 0);
+        assert_eq!(get_height_after(&directions, &rocks, DEFAULT_RULES, 1), 1);
+        assert_eq!(get_height_after(&directions, &rocks, DEFAULT_RULES, 2022), 3068);
+        assert_eq!(get_height_after(&directions, &rocks, DEFAULT_RULES, 1000000000000), 1514285714288);
+
+        // check the skipping ahead against actually doing it
+        let mut state = create_initial_state(&directions, &rocks, DEFAULT_RULES);
+        for count in 1..=500 {
             drop_rock(&mut state);
+            assert_eq!(get_height_after(&directions, &rocks, DEFAULT_RULES, count), state.tower_height + state.discarded_rows);
         }
-        assert_eq!(state.tower_height + state.discarded_rows, 3068);
     }
 }
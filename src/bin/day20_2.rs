@@ -1,30 +1,203 @@
-use std::{fs::File, io::{BufReader, BufRead}};
+use std::{fs::File, io::{BufReader, BufRead}, time::Instant};
+
+const INPUT_PATH: &str = "data/day20/input.txt";
+const DECRYPTION_KEY: i64 = 811589153;
+const ROUNDS: usize = 10;
 
 fn main() {
-    let file = File::open("data/day20/input.txt").unwrap();
+    let options = parse_options();
+
+    let file = File::open(&options.input_path).unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines();
-    let mut numbers = lines.map(|x| x.unwrap().parse::<i64>().unwrap()).enumerate().collect::<Vec<_>>();
+    let values = lines.map(|x| x.unwrap().parse::<i64>().unwrap() * DECRYPTION_KEY).collect::<Vec<_>>();
 
-    let decryption_key = 811589153;
+    let start_time = Instant::now();
+    let numbers = mix_with(options.mixer, &values, ROUNDS);
+    let elapsed = start_time.elapsed();
 
-    numbers.iter_mut().for_each(|x| x.1 *= decryption_key);
+    if options.benchmark {
+        println!("{:?} mixer took {:?}", options.mixer, elapsed);
 
-    // Index tracking original position -> position the number is at now
-    let mut numbers_index = numbers.iter().map(|x| x.0).collect::<Vec<_>>();
+        let other_mixer = match options.mixer {
+            Mixer::Swap => Mixer::Blocks,
+            Mixer::Blocks => Mixer::Swap,
+        };
+        let other_start_time = Instant::now();
+        let other_numbers = mix_with(other_mixer, &values, ROUNDS);
+        let other_elapsed = other_start_time.elapsed();
+        assert_eq!(rotate_to_zero(&numbers), rotate_to_zero(&other_numbers));
+        println!("{:?} mixer took {:?}", other_mixer, other_elapsed);
+    }
 
-    for _ in 0..10 {
-        mix(&mut numbers, &mut numbers_index);
+    println!("sum: {}", get_grove_sum(&numbers));
+}
+
+struct Options {
+    input_path: String,
+    mixer: Mixer,
+    // also run the other mixer, check it agrees and compare timings
+    benchmark: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Mixer {
+    // moves each number one swap at a time
+    Swap,
+    // removes and reinserts each number in a BlockList
+    Blocks,
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        input_path: INPUT_PATH.to_string(),
+        mixer: Mixer::Blocks,
+        benchmark: false,
+    };
+
+    for arg in std::env::args().skip(1) {
+        if let Some(s) = arg.strip_prefix("--input=") {
+            options.input_path = s.to_string();
+        }
+        else if let Some(s) = arg.strip_prefix("--mixer=") {
+            options.mixer = match s {
+                "swap" => Mixer::Swap,
+                "blocks" => Mixer::Blocks,
+                _ => panic!("unknown mixer: {}", s),
+            };
+        }
+        else if arg == "--benchmark" {
+            options.benchmark = true;
+        }
+        else {
+            panic!("unknown argument: {}", arg);
+        }
+    }
+
+    options
+}
+
+/// The numbers after mixing, starting from wherever the mixer happened to leave the start of the list.
+fn mix_with(mixer: Mixer, values: &[i64], rounds: usize) -> Vec<i64> {
+    match mixer {
+        Mixer::Swap => mix_swap(values, rounds),
+        Mixer::Blocks => mix_blocks(values, rounds),
     }
+}
 
-    let zero_idx = numbers.iter().position(|x| x.1 == 0).unwrap();
+fn get_grove_sum(numbers: &[i64]) -> i64 {
+    let zero_idx = numbers.iter().position(|x| *x == 0).unwrap();
     let c1_idx = (zero_idx + 1000) % numbers.len();
     let c2_idx = (zero_idx + 2000) % numbers.len();
     let c3_idx = (zero_idx + 3000) % numbers.len();
 
-    let sum = [c1_idx, c2_idx, c3_idx].into_iter().map(|i| numbers[i].1).sum::<i64>();
+    [c1_idx, c2_idx, c3_idx].into_iter().map(|i| numbers[i]).sum::<i64>()
+}
+
+/// The list is circular, so this gives mixers that leave it rotated differently something to agree on.
+fn rotate_to_zero(numbers: &[i64]) -> Vec<i64> {
+    let zero_idx = numbers.iter().position(|x| *x == 0).unwrap();
+    numbers[zero_idx..].iter().chain(numbers[..zero_idx].iter()).copied().collect()
+}
+
+fn mix_swap(values: &[i64], rounds: usize) -> Vec<i64> {
+    let mut numbers = values.iter().copied().enumerate().collect::<Vec<_>>();
+
+    // Index tracking original position -> position the number is at now
+    let mut numbers_index = numbers.iter().map(|x| x.0).collect::<Vec<_>>();
+
+    for _ in 0..rounds {
+        mix(&mut numbers, &mut numbers_index);
+    }
+
+    numbers.into_iter().map(|x| x.1).collect()
+}
+
+fn mix_blocks(values: &[i64], rounds: usize) -> Vec<i64> {
+    let mut list = BlockList::new(values.len());
+
+    // Moving by (len-1) places gets you back where you started, since the number itself isn't one of the places.
+    let cycle_len = (values.len() as i64) - 1;
+    if cycle_len > 0 {
+        for _ in 0..rounds {
+            for (item, value) in values.iter().enumerate() {
+                let pos = list.remove(item);
+                let new_pos = (pos as i64 + value).rem_euclid(cycle_len);
+                list.insert(new_pos as usize, item);
+            }
+        }
+    }
+
+    list.to_vec().into_iter().map(|item| values[item]).collect()
+}
+
+/// The items 0..n in some order, kept as runs of roughly sqrt(n) items
+/// so finding, removing or inserting an item only walks one run and the list of runs.
+struct BlockList {
+    blocks: Vec<Vec<usize>>,
+    // item -> index of the block it is in
+    block_of: Vec<usize>,
+    block_size: usize,
+}
+
+impl BlockList {
+    fn new(len: usize) -> Self {
+        let block_size = ((len as f64).sqrt() as usize).max(1);
+        let mut list = BlockList {
+            blocks: vec![(0..len).collect()],
+            block_of: vec![0; len],
+            block_size,
+        };
+        list.rebuild();
+        list
+    }
+
+    /// Splits everything back into even runs, after inserts have made one too long.
+    fn rebuild(&mut self) {
+        let items = self.to_vec();
+        self.blocks = items.chunks(self.block_size).map(|c| c.to_vec()).collect();
+        for (b, block) in self.blocks.iter().enumerate() {
+            for item in block {
+                self.block_of[*item] = b;
+            }
+        }
+    }
+
+    fn position(&self, item: usize) -> usize {
+        let b = self.block_of[item];
+        let before = self.blocks[..b].iter().map(|block| block.len()).sum::<usize>();
+        before + self.blocks[b].iter().position(|x| *x == item).unwrap()
+    }
+
+    /// Takes the item out and returns the position it was at.
+    fn remove(&mut self, item: usize) -> usize {
+        let pos = self.position(item);
+        let block = &mut self.blocks[self.block_of[item]];
+        let i = block.iter().position(|x| *x == item).unwrap();
+        block.remove(i);
+        pos
+    }
+
+    /// Puts the item in so that it ends up at pos, which can be one past the end.
+    fn insert(&mut self, mut pos: usize, item: usize) {
+        let mut b = 0;
+        while pos > self.blocks[b].len() && b + 1 < self.blocks.len() {
+            pos -= self.blocks[b].len();
+            b += 1;
+        }
+        assert!(pos <= self.blocks[b].len(), "position out of range");
+
+        self.blocks[b].insert(pos, item);
+        self.block_of[item] = b;
 
-    println!("sum: {}", sum);
+        if self.blocks[b].len() > 2 * self.block_size {
+            self.rebuild();
+        }
+    }
+
+    fn to_vec(&self) -> Vec<usize> {
+        self.blocks.iter().flatten().copied().collect()
+    }
 }
 
 fn mix(numbers: &mut [(usize, i64)], numbers_index: &mut [usize]) {
@@ -97,6 +270,55 @@ mod tests {
         assert_eq!(index, vec![1, 2, 3, 6, 0, 5, 4]);
     }
 
+    #[test]
+    fn test_mixers_agree_on_sample() {
+        let file = File::open("data/day20/sample_input.txt").unwrap();
+        let values = BufReader::new(file).lines().map(|x| x.unwrap().parse::<i64>().unwrap()).collect::<Vec<_>>();
+
+        // part 1 rules
+        let swapped = mix_with(Mixer::Swap, &values, 1);
+        let blocked = mix_with(Mixer::Blocks, &values, 1);
+        assert_eq!(rotate_to_zero(&swapped), vec![0, 3, -2, 1, 2, -3, 4]);
+        assert_eq!(rotate_to_zero(&blocked), vec![0, 3, -2, 1, 2, -3, 4]);
+        assert_eq!(get_grove_sum(&swapped), 3);
+        assert_eq!(get_grove_sum(&blocked), 3);
+
+        let values = values.iter().map(|x| x * DECRYPTION_KEY).collect::<Vec<_>>();
+        for rounds in 1..=ROUNDS {
+            assert_eq!(rotate_to_zero(&mix_with(Mixer::Swap, &values, rounds)), rotate_to_zero(&mix_with(Mixer::Blocks, &values, rounds)));
+        }
+        assert_eq!(get_grove_sum(&mix_with(Mixer::Swap, &values, ROUNDS)), 1623178306);
+        assert_eq!(get_grove_sum(&mix_with(Mixer::Blocks, &values, ROUNDS)), 1623178306);
+    }
+
+    #[test]
+    fn test_block_list() {
+        // small enough to have 3 blocks of 3
+        let mut list = BlockList::new(9);
+        assert_eq!(list.blocks.len(), 3);
+        assert_eq!(list.position(4), 4);
+
+        assert_eq!(list.remove(4), 4);
+        list.insert(0, 4);
+        assert_eq!(list.to_vec(), vec![4, 0, 1, 2, 3, 5, 6, 7, 8]);
+
+        assert_eq!(list.remove(0), 1);
+        list.insert(8, 0);
+        assert_eq!(list.to_vec(), vec![4, 1, 2, 3, 5, 6, 7, 8, 0]);
+        assert_eq!(list.position(0), 8);
+
+        // keep piling into the same block until it gets split up again
+        for item in [1, 2, 3, 5, 6, 7, 8] {
+            list.remove(item);
+            list.insert(1, item);
+        }
+        assert_eq!(list.to_vec(), vec![4, 8, 7, 6, 5, 3, 2, 1, 0]);
+        assert!(list.blocks.iter().all(|b| b.len() <= 2 * list.block_size));
+        for (i, item) in list.to_vec().into_iter().enumerate() {
+            assert_eq!(list.position(item), i);
+        }
+    }
+
     #[test]
     fn test_move_thing_forward() {
         let mut v = Vec::from_iter([1,2,3,4,5].into_iter().enumerate());
@@ -1,4 +1,6 @@
-use std::{fs::File, io::{BufReader, BufRead}, collections::{HashSet, VecDeque}};
+use std::{fs::File, io::{BufReader, BufRead}, collections::HashSet};
+
+use day1::search::astar;
 
 fn main() {
     let file = File::open("data/day24/input.txt").unwrap();
@@ -86,12 +88,6 @@ fn parse_blizzards<T: Iterator<Item=String>>(lines: T) -> (Vec<((i32, i32), Dire
     (v, w.try_into().unwrap(), h.try_into().unwrap())
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Entry {
-    state: State,
-    cost: i32,
-}
-
 fn wrap(val: i32, min: i32, max: i32) -> i32 {
     let base = val - min;
     let range = max - min + 1;
@@ -132,62 +128,29 @@ fn is_pos_in_bounds(blizzards: &Blizzards, pos: (i32,i32)) -> bool {
     pos.0 >= 1 && pos.0 <= blizzards.width-2 && pos.1 >= 1 && pos.1 <= blizzards.height - 2
 }
 
-fn get_successors(blizzards: &Blizzards, e: &Entry) -> Vec<Entry> {
+fn get_successors(blizzards: &Blizzards, state: &State) -> Vec<(State, i32)> {
     let new_states = [
-        State{pos: (e.state.pos.0,e.state.pos.1), time: e.state.time+1},
-        State{pos: (e.state.pos.0+1,e.state.pos.1), time: e.state.time+1},
-        State{pos: (e.state.pos.0-1,e.state.pos.1), time: e.state.time+1},
-        State{pos: (e.state.pos.0,e.state.pos.1+1), time: e.state.time+1},
-        State{pos: (e.state.pos.0,e.state.pos.1-1), time: e.state.time+1},
+        State{pos: (state.pos.0,state.pos.1), time: state.time+1},
+        State{pos: (state.pos.0+1,state.pos.1), time: state.time+1},
+        State{pos: (state.pos.0-1,state.pos.1), time: state.time+1},
+        State{pos: (state.pos.0,state.pos.1+1), time: state.time+1},
+        State{pos: (state.pos.0,state.pos.1-1), time: state.time+1},
     ];
 
     new_states.into_iter().filter(|s| is_pos_in_bounds(blizzards, s.pos) && !collides(blizzards, s))
-    .map(|s| Entry{cost:s.time, state: s})
+    .map(|s| (s, 1))
     .collect()
 }
 
+/// Returns the time at which we arrive at the goal.
 fn find_shortest_path(blizzards: &Blizzards, s: &State, goal: (i32, i32)) -> Option<i32> {
+    let result = astar(
+        [*s],
+        |state| get_successors(blizzards, state),
+        |state| (goal.0 - state.pos.0).abs() + (goal.1 - state.pos.1).abs(),
+        |state| state.pos == goal);
 
-    let mut open_list = VecDeque::new();
-    let mut closed_set = HashSet::new();
-
-    open_list.push_back(Entry{cost: 0, state: *s});
-
-    // let mut parents = HashMap::new();
-
-    loop {
-        match open_list.pop_front() {
-            None => return None,
-            Some(entry) if entry.state.pos == goal => {
-                // let mut parent = parents.get(&entry.state);
-                // while let Some(p) = parent {
-                //     println!("parent: {:?}", &p);
-                //     parent = parents.get(p);
-                // }
-
-                return Some(entry.cost);
-            }
-            Some(entry) => {
-                // println!("exploring: {:?}", &entry);
-                closed_set.insert(entry.state);
-                let successors = get_successors(blizzards, &entry);
-                for successor in &successors {
-                    if closed_set.contains(&successor.state) { continue; }
-                    let existing_elem = open_list.iter().enumerate().find(|(_, elem)| elem.state == successor.state);
-                    match existing_elem {
-                        Some((_, elem)) if elem.cost <= successor.cost => { continue; }
-                        Some((i, _)) => { open_list.remove(i); }
-                        None => {}
-                    };
-
-                    let idx = open_list.iter().enumerate().find_map(|(i, elem)| if elem.cost > successor.cost { Some(i) } else { None });
-                    open_list.insert(idx.unwrap_or(open_list.len()), *successor);
-
-                    // parents.insert(successor.state, entry.state);
-                }
-            }
-        }
-    }
+    result.goal.map(|state| state.time)
 }
 
 
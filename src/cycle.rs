@@ -0,0 +1,149 @@
+use std::{collections::{HashMap, hash_map::Entry}, hash::Hash};
+
+/// Where a sequence starts repeating itself, counting from 0.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cycle {
+    /// Index of the first element that is part of the cycle.
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Maps an index anywhere in the sequence back to the index it repeats within the first lap of the cycle.
+    pub fn fold_index(&self, index: usize) -> usize {
+        if index < self.start {
+            index
+        }
+        else {
+            self.start + ((index - self.start) % self.length)
+        }
+    }
+
+    /// How many whole laps of the cycle come before index.
+    pub fn laps_before(&self, index: usize) -> usize {
+        index.saturating_sub(self.start) / self.length
+    }
+}
+
+/// Finds the first repeat in a sequence of keys, remembering every key seen so far.
+/// This is the one to use when the key is a summary of a bigger state, since the state itself never has to repeat.
+/// Returns None if the sequence ends first.
+pub fn find_cycle<K: Hash + Eq>(keys: impl IntoIterator<Item=K>) -> Option<Cycle> {
+    let mut seen = HashMap::new();
+    for (i, key) in keys.into_iter().enumerate() {
+        match seen.entry(key) {
+            Entry::Occupied(e) => { return Some(Cycle { start: *e.get(), length: i - e.get() }); }
+            Entry::Vacant(e) => { e.insert(i); }
+        }
+    }
+    None
+}
+
+/// Floyd's tortoise and hare, for a sequence x0, f(x0), f(f(x0)), ... that must eventually repeat.
+/// Only ever keeps two values around.
+pub fn find_cycle_floyd<T: PartialEq + Clone, F: FnMut(&T) -> T>(x0: T, mut f: F) -> Cycle {
+    let mut tortoise = f(&x0);
+    let mut hare = f(&tortoise);
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        hare = f(&hare);
+    }
+
+    // the distance between them is now a multiple of the length,
+    // so walking both at the same speed from the start and from the meeting point lines them up at the start of the cycle
+    let mut start = 0;
+    tortoise = x0;
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        start += 1;
+    }
+
+    let mut length = 1;
+    hare = f(&tortoise);
+    while tortoise != hare {
+        hare = f(&hare);
+        length += 1;
+    }
+
+    Cycle { start, length }
+}
+
+/// Brent's algorithm, same contract as find_cycle_floyd but usually calls f fewer times.
+pub fn find_cycle_brent<T: PartialEq + Clone, F: FnMut(&T) -> T>(x0: T, mut f: F) -> Cycle {
+    // look for the length first, teleporting the tortoise each time the hare covers a power of two
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = x0.clone();
+    let mut hare = f(&x0);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = f(&hare);
+        length += 1;
+    }
+
+    // then walk two pointers length apart from the start until they meet
+    let mut start = 0;
+    tortoise = x0.clone();
+    hare = x0;
+    for _ in 0..length {
+        hare = f(&hare);
+    }
+    while tortoise != hare {
+        tortoise = f(&tortoise);
+        hare = f(&hare);
+        start += 1;
+    }
+
+    Cycle { start, length }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0, 1, 2, 3, 4, 5, 6, 7, then 3 onwards again
+    fn rho(x: &u32) -> u32 {
+        if *x == 7 { 3 } else { x + 1 }
+    }
+
+    #[test]
+    fn test_find_cycle_by_key() {
+        assert_eq!(find_cycle(std::iter::successors(Some(0), |x| Some(rho(x)))), Some(Cycle { start: 3, length: 5 }));
+        assert_eq!(find_cycle([1, 2, 3]), None);
+        assert_eq!(find_cycle("abcb".chars()), Some(Cycle { start: 1, length: 2 }));
+    }
+
+    #[test]
+    fn test_floyd_and_brent_agree() {
+        assert_eq!(find_cycle_floyd(0, rho), Cycle { start: 3, length: 5 });
+        assert_eq!(find_cycle_brent(0, rho), Cycle { start: 3, length: 5 });
+
+        // a pure cycle with no lead-in
+        assert_eq!(find_cycle_floyd(0, |x| (x + 1) % 4), Cycle { start: 0, length: 4 });
+        assert_eq!(find_cycle_brent(0, |x| (x + 1) % 4), Cycle { start: 0, length: 4 });
+
+        for seed in [0u64, 1, 7, 1234] {
+            let f = |x: &u64| (x * x + 1) % 255;
+            let expected = find_cycle(std::iter::successors(Some(seed), |x| Some(f(x)))).unwrap();
+            assert_eq!(find_cycle_floyd(seed, f), expected);
+            assert_eq!(find_cycle_brent(seed, f), expected);
+        }
+    }
+
+    #[test]
+    fn test_fold_index() {
+        let cycle = Cycle { start: 3, length: 5 };
+        assert_eq!(cycle.fold_index(2), 2);
+        assert_eq!(cycle.fold_index(3), 3);
+        assert_eq!(cycle.fold_index(8), 3);
+        assert_eq!(cycle.fold_index(14), 4);
+        assert_eq!(cycle.laps_before(2), 0);
+        assert_eq!(cycle.laps_before(14), 2);
+    }
+}
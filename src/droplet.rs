@@ -0,0 +1,388 @@
+use std::{collections::{HashSet, HashMap}, fmt::Write};
+
+use crate::search::bfs;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub struct Position {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+impl Position {
+    pub fn new(x: i64, y: i64, z: i64) -> Self {
+        Position { x, y, z }
+    }
+
+    fn offset(&self, dx: i64, dy: i64, dz: i64) -> Position {
+        Position::new(self.x + dx, self.y + dy, self.z + dz)
+    }
+
+    pub fn neighbours(&self) -> [Position; 6] {
+        [
+            Position::new(self.x + 1, self.y, self.z),
+            Position::new(self.x - 1, self.y, self.z),
+            Position::new(self.x, self.y + 1, self.z),
+            Position::new(self.x, self.y - 1, self.z),
+            Position::new(self.x, self.y, self.z + 1),
+            Position::new(self.x, self.y, self.z - 1),
+        ]
+    }
+}
+
+/// Parses a line like "2,2,2".
+pub fn parse_position(line: &str) -> Result<Position, String> {
+    let parts = line.trim().split(',').map(|s| s.trim().parse::<i64>().map_err(|e| format!("{:?}: {}", s, e))).collect::<Result<Vec<_>, _>>()?;
+    match parts[..] {
+        [x, y, z] => Ok(Position::new(x, y, z)),
+        _ => Err(format!("expected 3 coordinates, got {}", parts.len())),
+    }
+}
+
+/// What fills a cell, as far as the droplet is concerned.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Cell {
+    Lava,
+    /// Air connected to the outside world.
+    Exterior,
+    /// Air trapped inside the droplet, in the pocket with this index.
+    Interior(usize),
+}
+
+/// A connected region of trapped air.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Pocket {
+    /// Sorted, so the first one is the lowest corner.
+    pub cells: Vec<Position>,
+    pub volume: usize,
+    /// Faces of lava cubes that face into the pocket.
+    pub surface_area: usize,
+}
+
+impl Pocket {
+    /// The lava faces around the pocket, pointing into it.
+    pub fn faces(&self, droplet: &Droplet) -> Vec<Face> {
+        get_faces_towards(droplet, self.cells.iter())
+    }
+}
+
+/// One side of a unit cube of lava, facing out into the air along normal.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub struct Face {
+    pub cube: Position,
+    pub normal: Position,
+}
+
+impl Face {
+    /// Corners in anticlockwise order when looking at the face from the air side,
+    /// with the cube at x,y,z covering x..x+1 and so on.
+    pub fn corners(&self) -> [Position; 4] {
+        let Position { x, y, z } = self.cube;
+        // two edges whose cross product is the normal
+        let (base, u, v) = match (self.normal.x, self.normal.y, self.normal.z) {
+            (1, 0, 0) => (Position::new(x + 1, y, z), (0, 1, 0), (0, 0, 1)),
+            (-1, 0, 0) => (Position::new(x, y, z), (0, 0, 1), (0, 1, 0)),
+            (0, 1, 0) => (Position::new(x, y + 1, z), (0, 0, 1), (1, 0, 0)),
+            (0, -1, 0) => (Position::new(x, y, z), (1, 0, 0), (0, 0, 1)),
+            (0, 0, 1) => (Position::new(x, y, z + 1), (1, 0, 0), (0, 1, 0)),
+            (0, 0, -1) => (Position::new(x, y, z), (0, 1, 0), (1, 0, 0)),
+            _ => panic!("not a unit normal: {:?}", self.normal),
+        };
+        [
+            base,
+            base.offset(u.0, u.1, u.2),
+            base.offset(u.0 + v.0, u.1 + v.1, u.2 + v.2),
+            base.offset(v.0, v.1, v.2),
+        ]
+    }
+}
+
+/// Faces of lava next to any of the given air cells, sorted.
+fn get_faces_towards<'a>(droplet: &Droplet, air: impl Iterator<Item=&'a Position>) -> Vec<Face> {
+    let mut faces = air
+        .flat_map(|a| a.neighbours().into_iter()
+            .filter(|n| droplet.cubes.contains(n))
+            .map(move |n| Face { cube: n, normal: Position::new(a.x - n.x, a.y - n.y, a.z - n.z) }))
+        .collect::<Vec<_>>();
+    faces.sort();
+    faces
+}
+
+/// A Wavefront OBJ file with one object for each named set of faces, each face a quad.
+pub fn write_obj(objects: &[(String, Vec<Face>)]) -> String {
+    let mut s = String::new();
+    let mut vertex_indices = HashMap::new();
+    for (name, faces) in objects {
+        writeln!(s, "o {}", name).unwrap();
+        for face in faces {
+            let mut indices = Vec::new();
+            for corner in face.corners() {
+                let next_index = vertex_indices.len() + 1;
+                let index = *vertex_indices.entry(corner).or_insert_with(|| {
+                    writeln!(s, "v {} {} {}", corner.x, corner.y, corner.z).unwrap();
+                    next_index
+                });
+                indices.push(index.to_string());
+            }
+            writeln!(s, "f {}", indices.join(" ")).unwrap();
+        }
+    }
+    s
+}
+
+/// An ASCII STL file with one solid for each named set of faces, each face split into two triangles.
+pub fn write_stl(objects: &[(String, Vec<Face>)]) -> String {
+    let mut s = String::new();
+    for (name, faces) in objects {
+        writeln!(s, "solid {}", name).unwrap();
+        for face in faces {
+            let c = face.corners();
+            for triangle in [[c[0], c[1], c[2]], [c[0], c[2], c[3]]] {
+                writeln!(s, "  facet normal {} {} {}", face.normal.x, face.normal.y, face.normal.z).unwrap();
+                writeln!(s, "    outer loop").unwrap();
+                for v in triangle {
+                    writeln!(s, "      vertex {} {} {}", v.x, v.y, v.z).unwrap();
+                }
+                writeln!(s, "    endloop").unwrap();
+                writeln!(s, "  endfacet").unwrap();
+            }
+        }
+        writeln!(s, "endsolid {}", name).unwrap();
+    }
+    s
+}
+
+#[derive(Debug)]
+pub struct Droplet {
+    cubes: HashSet<Position>,
+    // one cell of air all the way around the cubes, so the outside is connected
+    min: Position,
+    max: Position,
+}
+
+impl Droplet {
+    pub fn new(cubes: impl IntoIterator<Item=Position>) -> Self {
+        let cubes = cubes.into_iter().collect::<HashSet<_>>();
+        let min = cubes.iter().copied().reduce(|a, b| Position::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)))
+            .map_or(Position::new(0, 0, 0), |p| Position::new(p.x - 1, p.y - 1, p.z - 1));
+        let max = cubes.iter().copied().reduce(|a, b| Position::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)))
+            .map_or(Position::new(0, 0, 0), |p| Position::new(p.x + 1, p.y + 1, p.z + 1));
+        Droplet { cubes, min, max }
+    }
+
+    pub fn cubes(&self) -> &HashSet<Position> {
+        &self.cubes
+    }
+
+    /// Every face of a cube that doesn't touch another cube, whether or not the outside can reach it.
+    pub fn surface_area(&self) -> usize {
+        self.cubes.iter()
+            .map(|p| p.neighbours().iter().filter(|n| !self.cubes.contains(n)).count())
+            .sum()
+    }
+
+    fn is_in_bounds(&self, p: &Position) -> bool {
+        p.x >= self.min.x && p.y >= self.min.y && p.z >= self.min.z
+            && p.x <= self.max.x && p.y <= self.max.y && p.z <= self.max.z
+    }
+
+    fn get_air_neighbours(&self, p: &Position) -> Vec<Position> {
+        p.neighbours().into_iter().filter(|n| self.is_in_bounds(n) && !self.cubes.contains(n)).collect()
+    }
+
+    /// Floods the air around the droplet from outside, then groups whatever air is left into pockets.
+    pub fn analyse(&self) -> Analysis {
+        let exterior = bfs([self.min], |p| self.get_air_neighbours(p), |_| false)
+            .distances
+            .into_keys()
+            .collect::<HashSet<_>>();
+
+        let mut pockets = Vec::new();
+        let mut pocket_indices = HashMap::new();
+        for x in self.min.x..=self.max.x {
+            for y in self.min.y..=self.max.y {
+                for z in self.min.z..=self.max.z {
+                    let p = Position::new(x, y, z);
+                    if self.cubes.contains(&p) || exterior.contains(&p) || pocket_indices.contains_key(&p) {
+                        continue;
+                    }
+
+                    let mut cells = bfs([p], |p| self.get_air_neighbours(p), |_| false)
+                        .distances
+                        .into_keys()
+                        .collect::<Vec<_>>();
+                    cells.sort();
+                    for cell in &cells {
+                        pocket_indices.insert(*cell, pockets.len());
+                    }
+                    let surface_area = cells.iter()
+                        .map(|c| c.neighbours().iter().filter(|n| self.cubes.contains(n)).count())
+                        .sum();
+                    pockets.push(Pocket { volume: cells.len(), cells, surface_area });
+                }
+            }
+        }
+
+        Analysis { exterior, pockets, pocket_indices }
+    }
+}
+
+/// Where all the air in and around a droplet is.
+#[derive(Debug)]
+pub struct Analysis {
+    /// Air reachable from outside, within one cell of the droplet's bounding box.
+    pub exterior: HashSet<Position>,
+    /// Ordered by their lowest corner.
+    pub pockets: Vec<Pocket>,
+    pocket_indices: HashMap<Position, usize>,
+}
+
+impl Analysis {
+    /// Anything outside the area that was flooded counts as exterior.
+    pub fn classify(&self, droplet: &Droplet, p: &Position) -> Cell {
+        if droplet.cubes.contains(p) {
+            Cell::Lava
+        }
+        else if let Some(i) = self.pocket_indices.get(p) {
+            Cell::Interior(*i)
+        }
+        else {
+            Cell::Exterior
+        }
+    }
+
+    /// Faces of the droplet that the outside can reach.
+    pub fn exterior_surface_area(&self, droplet: &Droplet) -> usize {
+        self.exterior.iter()
+            .map(|p| p.neighbours().iter().filter(|n| droplet.cubes.contains(n)).count())
+            .sum()
+    }
+
+    /// The faces that exterior_surface_area counts, pointing outwards.
+    pub fn exterior_faces(&self, droplet: &Droplet) -> Vec<Face> {
+        get_faces_towards(droplet, self.exterior.iter())
+    }
+
+    pub fn interior_volume(&self) -> usize {
+        self.pockets.iter().map(|p| p.volume).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_sample() -> Droplet {
+        let s = std::fs::read_to_string("data/day18/sample_input.txt").unwrap();
+        Droplet::new(s.lines().map(|l| parse_position(l).unwrap()))
+    }
+
+    #[test]
+    fn test_sample() {
+        let droplet = load_sample();
+        assert_eq!(droplet.surface_area(), 64);
+
+        let analysis = droplet.analyse();
+        assert_eq!(analysis.exterior_surface_area(&droplet), 58);
+        assert_eq!(analysis.pockets, vec![Pocket { cells: vec![Position::new(2, 2, 5)], volume: 1, surface_area: 6 }]);
+        assert_eq!(analysis.classify(&droplet, &Position::new(2, 2, 5)), Cell::Interior(0));
+        assert_eq!(analysis.classify(&droplet, &Position::new(2, 2, 2)), Cell::Lava);
+        assert_eq!(analysis.classify(&droplet, &Position::new(0, 0, 0)), Cell::Exterior);
+        assert_eq!(analysis.classify(&droplet, &Position::new(100, 0, 0)), Cell::Exterior);
+    }
+
+    // A hollow 5x5x5 box with a wall down the middle, far from the origin.
+    fn hollow_box(origin: Position) -> Droplet {
+        let mut cubes = Vec::new();
+        for x in 0..5 {
+            for y in 0..5 {
+                for z in 0..5 {
+                    let on_edge = [x, y, z].iter().any(|c| *c == 0 || *c == 4);
+                    if on_edge || x == 2 {
+                        cubes.push(Position::new(origin.x + x, origin.y + y, origin.z + z));
+                    }
+                }
+            }
+        }
+        Droplet::new(cubes)
+    }
+
+    #[test]
+    fn test_separate_pockets() {
+        let origin = Position::new(-3000000000, 5000000000, -7);
+        let droplet = hollow_box(origin);
+        let analysis = droplet.analyse();
+
+        // two 1x3x3 halves, each with 6 walls' worth of faces around it
+        assert_eq!(analysis.pockets.len(), 2);
+        for pocket in &analysis.pockets {
+            assert_eq!(pocket.volume, 9);
+            assert_eq!(pocket.surface_area, 2 * 9 + 4 * 3);
+        }
+        assert_eq!(analysis.pockets[0].cells[0], Position::new(origin.x + 1, origin.y + 1, origin.z + 1));
+        assert_eq!(analysis.classify(&droplet, &Position::new(origin.x + 3, origin.y + 2, origin.z + 2)), Cell::Interior(1));
+        assert_eq!(analysis.interior_volume(), 18);
+
+        assert_eq!(analysis.exterior_surface_area(&droplet), 6 * 25);
+        let interior_area = analysis.pockets.iter().map(|p| p.surface_area).sum::<usize>();
+        assert_eq!(droplet.surface_area(), analysis.exterior_surface_area(&droplet) + interior_area);
+    }
+
+    #[test]
+    fn test_face_corners_wind_towards_normal() {
+        let cube = Position::new(3, -4, 5);
+        for normal in Position::new(0, 0, 0).neighbours() {
+            let c = Face { cube, normal }.corners();
+            let u = (c[1].x - c[0].x, c[1].y - c[0].y, c[1].z - c[0].z);
+            let v = (c[3].x - c[0].x, c[3].y - c[0].y, c[3].z - c[0].z);
+            let cross = Position::new(u.1 * v.2 - u.2 * v.1, u.2 * v.0 - u.0 * v.2, u.0 * v.1 - u.1 * v.0);
+            assert_eq!(cross, normal);
+
+            // all four corners are on the side of the cube the normal points to
+            let centre_twice = Position::new(2 * cube.x + 1 + normal.x, 2 * cube.y + 1 + normal.y, 2 * cube.z + 1 + normal.z);
+            let sum = c.iter().fold(Position::new(0, 0, 0), |a, p| a.offset(p.x, p.y, p.z));
+            assert_eq!(sum, Position::new(centre_twice.x * 2, centre_twice.y * 2, centre_twice.z * 2));
+        }
+    }
+
+    #[test]
+    fn test_export_single_cube() {
+        let droplet = Droplet::new([Position::new(0, 0, 0)]);
+        let faces = droplet.analyse().exterior_faces(&droplet);
+        assert_eq!(faces.len(), 6);
+
+        let obj = write_obj(&[("droplet".to_string(), faces.clone())]);
+        assert!(obj.starts_with("o droplet\n"));
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 6);
+        assert!(obj.contains("v 1 1 1\n"));
+
+        let stl = write_stl(&[("droplet".to_string(), faces)]);
+        assert!(stl.starts_with("solid droplet\n  facet normal -1 0 0\n    outer loop\n      vertex 0 0 0\n"));
+        assert!(stl.ends_with("endsolid droplet\n"));
+        assert_eq!(stl.matches("facet normal").count(), 12);
+    }
+
+    #[test]
+    fn test_export_sample() {
+        let droplet = load_sample();
+        let analysis = droplet.analyse();
+        let exterior = analysis.exterior_faces(&droplet);
+        assert_eq!(exterior.len(), 58);
+        let pocket = analysis.pockets[0].faces(&droplet);
+        assert_eq!(pocket.len(), 6);
+        // the pocket's faces point into the pocket
+        assert!(pocket.iter().all(|f| f.cube.offset(f.normal.x, f.normal.y, f.normal.z) == Position::new(2, 2, 5)));
+
+        let obj = write_obj(&[("exterior".to_string(), exterior), ("pocket_1".to_string(), pocket)]);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("o ")).collect::<Vec<_>>(), vec!["o exterior", "o pocket_1"]);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 64);
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_position("1,-2,300000000000"), Ok(Position::new(1, -2, 300000000000)));
+        assert!(parse_position("1,2").is_err());
+        assert!(parse_position("1,x,3").is_err());
+    }
+}
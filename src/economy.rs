@@ -0,0 +1,633 @@
+use std::cmp::Ordering;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Most resources a blueprint can name. States keep a fixed-size array of each so they're cheap to copy.
+pub const MAX_RESOURCES: usize = 8;
+
+/// How to build one kind of robot. Each robot collects one unit of its resource per minute.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Recipe {
+    /// Index of the resource the robot collects.
+    pub robot: usize,
+    /// Resource index and quantity, in the order they were written, with any resource named twice added up.
+    pub costs: Vec<(usize, i32)>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Blueprint {
+    pub id: i32,
+    /// Names in the order they first turn up in the blueprint.
+    pub resources: Vec<String>,
+    pub recipes: Vec<Recipe>,
+    /// The most of each resource any recipe asks for. Once we collect this much every minute there's no point building more of its robot,
+    /// since we can only build one robot a minute.
+    max_spend: [i32; MAX_RESOURCES],
+}
+
+impl Blueprint {
+    pub fn resource_index(&self, name: &str) -> Option<usize> {
+        self.resources.iter().position(|r| r == name)
+    }
+}
+
+fn get_or_add_resource(resources: &mut Vec<String>, name: &str) -> usize {
+    match resources.iter().position(|r| r == name) {
+        Some(i) => i,
+        None => {
+            resources.push(name.to_string());
+            resources.len() - 1
+        }
+    }
+}
+
+lazy_static! {
+    static ref HEADER_REGEX: Regex = Regex::new(r"^Blueprint (\d+):(.*)$").unwrap();
+    static ref RECIPE_REGEX: Regex = Regex::new(r"^Each ([\w-]+) robot costs (.+)$").unwrap();
+    static ref COST_SEPARATOR_REGEX: Regex = Regex::new(r",\s*(?:and\s+)?|\s+and\s+").unwrap();
+    static ref COST_REGEX: Regex = Regex::new(r"^(\d+) ([\w-]+)$").unwrap();
+}
+
+/// Parses a line like "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore and 1 sand."
+/// Any number of recipes and up to MAX_RESOURCES resources are allowed, and costs can be a list like "1 ore, 2 clay and 3 sand".
+pub fn parse_blueprint(line: &str) -> Result<Blueprint, String> {
+    let captures = HEADER_REGEX.captures(line.trim()).ok_or_else(|| format!("not a blueprint: {:?}", line))?;
+    let id = captures[1].parse::<i32>().map_err(|e| format!("{:?}: {}", &captures[1], e))?;
+
+    let mut resources = Vec::new();
+    let mut recipes = Vec::new();
+    for sentence in captures[2].split('.').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let recipe_captures = RECIPE_REGEX.captures(sentence).ok_or_else(|| format!("not a recipe: {:?}", sentence))?;
+        let robot = get_or_add_resource(&mut resources, &recipe_captures[1]);
+
+        let mut costs = Vec::new();
+        for cost in COST_SEPARATOR_REGEX.split(&recipe_captures[2]) {
+            let cost_captures = COST_REGEX.captures(cost.trim()).ok_or_else(|| format!("not a cost: {:?}", cost))?;
+            let quantity = cost_captures[1].parse::<i32>().map_err(|e| format!("{:?}: {}", &cost_captures[1], e))?;
+            let resource = get_or_add_resource(&mut resources, &cost_captures[2]);
+            match costs.iter_mut().find(|(i, _)| *i == resource) {
+                Some((_, n)) => *n += quantity,
+                None => costs.push((resource, quantity)),
+            }
+        }
+
+        recipes.push(Recipe { robot, costs });
+    }
+
+    if recipes.is_empty() {
+        return Err(format!("blueprint {} has no recipes", id));
+    }
+    if resources.len() > MAX_RESOURCES {
+        return Err(format!("blueprint {} has {} resources, at most {} are supported", id, resources.len(), MAX_RESOURCES));
+    }
+
+    let mut max_spend = [0; MAX_RESOURCES];
+    for (i, n) in recipes.iter().flat_map(|r| r.costs.iter()) {
+        max_spend[*i] = max_spend[*i].max(*n);
+    }
+
+    Ok(Blueprint { id, resources, recipes, max_spend })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct State {
+    /// Indexed like Blueprint::resources, as is robots. Anything past the blueprint's resources stays at 0.
+    pub resources: [i32; MAX_RESOURCES],
+    pub robots: [i32; MAX_RESOURCES],
+    pub time_remaining: i32,
+}
+
+impl State {
+    /// Nothing collected yet and a single robot, the one that collects starting_robot.
+    pub fn new(blueprint: &Blueprint, starting_robot: usize, minutes: i32) -> Self {
+        assert!(starting_robot < blueprint.resources.len(), "blueprint {} only has {} resources", blueprint.id, blueprint.resources.len());
+        let mut robots = [0; MAX_RESOURCES];
+        robots[starting_robot] = 1;
+        State {
+            resources: [0; MAX_RESOURCES],
+            robots,
+            time_remaining: minutes,
+        }
+    }
+
+    fn can_afford(&self, recipe: &Recipe) -> bool {
+        recipe.costs.iter().all(|(i, n)| self.resources[*i] >= *n)
+    }
+
+    /// Every robot collects for the given number of minutes, with nothing built.
+    fn collect_for(&mut self, minutes: i32) {
+        for (resource, robots) in self.resources.iter_mut().zip(self.robots.iter()) {
+            *resource += robots * minutes;
+        }
+        self.time_remaining -= minutes;
+    }
+
+    /// Minutes of collecting before we can afford the recipe, or None if we're missing a robot we'd need.
+    fn time_until_affordable(&self, recipe: &Recipe) -> Option<i32> {
+        let mut wait = 0;
+        for (i, n) in &recipe.costs {
+            let shortfall = n - self.resources[*i];
+            if shortfall <= 0 {
+                continue;
+            }
+            if self.robots[*i] == 0 {
+                return None;
+            }
+            wait = wait.max((shortfall + self.robots[*i] - 1) / self.robots[*i]);
+        }
+        Some(wait)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    DoNothing,
+    /// Index into Blueprint::recipes.
+    Build(usize),
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct Plan {
+    pub actions: Vec<Action>,
+}
+
+/// Spends whatever the action costs, lets every robot collect, then finishes off the new robot.
+pub fn tick(state: &mut State, blueprint: &Blueprint, action: Action) {
+    assert!(state.time_remaining > 0);
+
+    let recipe = match action {
+        Action::DoNothing => None,
+        Action::Build(r) => Some(&blueprint.recipes[r]),
+    };
+
+    if let Some(recipe) = recipe {
+        assert!(state.can_afford(recipe));
+        for (i, n) in &recipe.costs {
+            state.resources[*i] -= n;
+        }
+    }
+
+    for (resource, robots) in state.resources.iter_mut().zip(state.robots.iter()) {
+        *resource += robots;
+    }
+
+    if let Some(recipe) = recipe {
+        state.robots[recipe.robot] += 1;
+    }
+
+    state.time_remaining -= 1;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Calls to find_max_resource.
+    pub nodes_expanded: usize,
+    /// Successors skipped because get_max_possible_resource said they couldn't beat the best so far.
+    pub nodes_pruned: usize,
+}
+
+/// One step of the plan find_max_resource is working on, pointing back at the step before it.
+#[derive(Debug, Clone, Copy)]
+struct PlanStep {
+    previous: Option<usize>,
+    /// Minutes spent collecting before building.
+    wait: i32,
+    /// Index into Blueprint::recipes, or None for sitting out the rest of the time.
+    build: Option<usize>,
+}
+
+/// Steps for the plan being searched and for the best one found so far, and nothing else.
+struct PlanSteps {
+    steps: Vec<PlanStep>,
+    /// The most of the goal resource seen anywhere yet, and the last step on the way there.
+    best: (i32, Option<usize>),
+}
+
+/// Waits until the recipe can be afforded and then builds it.
+/// Building on the last minute is pointless, so that's not allowed.
+fn try_build(state: &State, blueprint: &Blueprint, goal: usize, recipe_index: usize) -> Option<(State, PlanStep)> {
+    let recipe = &blueprint.recipes[recipe_index];
+    if recipe.robot != goal && state.robots[recipe.robot] >= blueprint.max_spend[recipe.robot] {
+        return None;
+    }
+
+    let wait = state.time_until_affordable(recipe)?;
+    if state.time_remaining - wait <= 1 {
+        return None;
+    }
+
+    // the same as waiting and then a tick that builds it, without going minute by minute
+    let mut s = *state;
+    s.collect_for(wait + 1);
+    for (i, n) in &recipe.costs {
+        s.resources[*i] -= n;
+    }
+    s.robots[recipe.robot] += 1;
+
+    Some((s, PlanStep { previous: None, wait, build: Some(recipe_index) }))
+}
+
+/// Each successor is the next robot we could build, or sitting out the rest of the time if that gets us anything,
+/// along with the step that gets there. The steps aren't linked up to anything yet.
+fn get_successors(state: &State, blueprint: &Blueprint, goal: usize) -> Vec<(State, PlanStep)> {
+    let mut successors = Vec::with_capacity(blueprint.recipes.len() + 1);
+    successors.extend((0..blueprint.recipes.len()).filter_map(|r| try_build(state, blueprint, goal, r)));
+
+    if state.time_remaining > 0 && state.robots[goal] > 0 {
+        let mut s = *state;
+        s.collect_for(state.time_remaining);
+        successors.push((s, PlanStep { previous: None, wait: state.time_remaining, build: None }));
+    }
+
+    successors
+}
+
+/// Returns the most of the goal resource we can end up with, and what to do each minute from here to get it.
+pub fn find_max_resource(state: &State, blueprint: &Blueprint, goal: usize, stats: &mut SearchStats) -> (i32, Plan) {
+    let mut plan_steps = PlanSteps { steps: Vec::new(), best: (i32::MIN, None) };
+    let most = find_max_resource_rec(state, None, blueprint, goal, stats, &mut plan_steps);
+    assert_eq!(most, plan_steps.best.0);
+
+    let mut steps = Vec::new();
+    let mut step = plan_steps.best.1;
+    while let Some(i) = step {
+        steps.push(plan_steps.steps[i]);
+        step = plan_steps.steps[i].previous;
+    }
+
+    let mut actions = Vec::new();
+    for step in steps.iter().rev() {
+        actions.resize(actions.len() + usize::try_from(step.wait).unwrap(), Action::DoNothing);
+        if let Some(r) = step.build {
+            actions.push(Action::Build(r));
+        }
+    }
+    // if there's nothing worth doing after the last step, just wait out the rest of the time
+    actions.resize(state.time_remaining.try_into().unwrap(), Action::DoNothing);
+
+    (most, Plan { actions })
+}
+
+/// step is the last step taken to get to state.
+fn find_max_resource_rec(state: &State, step: Option<usize>, blueprint: &Blueprint, goal: usize, stats: &mut SearchStats, plan_steps: &mut PlanSteps) -> i32 {
+    stats.nodes_expanded += 1;
+
+    // Searching depth first, the first place we see a new best is on the same plan that picking the first
+    // best successor all the way down would give.
+    if state.resources[goal] > plan_steps.best.0 {
+        plan_steps.best = (state.resources[goal], step);
+    }
+
+    let mut successors = get_successors(state, blueprint, goal);
+    successors.sort_unstable_by(|a, b| cmp_states(&a.0, &b.0, goal));
+
+    let mut most = state.resources[goal];
+    for (succ, succ_step) in successors.iter().rev() {
+        let potential = get_max_possible_resource(succ, blueprint, goal);
+        if potential < most {
+            stats.nodes_pruned += 1;
+            continue;
+        }
+
+        let index = plan_steps.steps.len();
+        plan_steps.steps.push(PlanStep { previous: step, ..*succ_step });
+        let actual = find_max_resource_rec(succ, Some(index), blueprint, goal, stats, plan_steps);
+
+        assert!(potential >= actual);
+
+        // Only the steps leading to the best so far need to stay around.
+        if plan_steps.best.1.is_none_or(|best| best < index) {
+            plan_steps.steps.truncate(index);
+        }
+
+        most = most.max(actual);
+    }
+
+    most
+}
+
+/// An upper bound on how much of the goal resource we could end up with:
+/// what the goal robots we already have collect, plus pretending we can build another goal robot every minute
+/// once we have the robots its recipes need.
+pub fn get_max_possible_resource(s: &State, blueprint: &Blueprint, goal: usize) -> i32 {
+    // for each input we have no robots for yet, we will need to build one first,
+    // which can't happen before we could afford it even building one of each of its inputs every minute
+    let delay = blueprint.recipes.iter()
+        .filter(|r| r.robot == goal)
+        .map(|goal_recipe| {
+            goal_recipe.costs.iter()
+                .filter(|(i, n)| *n > 0 && s.robots[*i] == 0 && s.resources[*i] < *n)
+                .map(|(i, _)| {
+                    blueprint.recipes.iter()
+                        .filter(|r| r.robot == *i)
+                        .map(|r| {
+                            let time_to_afford = r.costs.iter().map(|(j, n)| {
+                                get_max_resource_seq(s.resources[*j], s.robots[*j]).take_while(|x| x < n).count() as i32
+                            }).max().unwrap_or(0);
+                            1 + time_to_afford
+                        })
+                        .min()
+                        .unwrap_or(s.time_remaining)
+                })
+                .max()
+                .unwrap_or(0)
+        })
+        .min()
+        .unwrap_or(s.time_remaining);
+
+    let collected = s.resources[goal] + s.robots[goal] * s.time_remaining;
+
+    let time_available = s.time_remaining - delay;
+    if time_available < 1 {
+        return collected;
+    }
+
+    // each new robot collects for one minute less than the one before it,
+    // which is get_max_resource_seq(0, 0).nth(time_available) without stepping through it
+    collected + (time_available * (time_available - 1)) / 2
+}
+
+/// Sequence of total resource at the start of each turn assuming we start from X robots
+/// and build a new robot of that collecting type every turn.
+pub fn get_max_resource_seq(initial_resource: i32, initial_robots: i32) -> impl Iterator<Item=i32> {
+    (initial_robots..).scan(initial_resource, |resource_total, robots_this_turn| {
+        let current_resource = *resource_total;
+        *resource_total += robots_this_turn;
+        Some(current_resource)
+    })
+}
+
+/// Goal robots matter most, then the later resources, which tend to be the expensive ones.
+fn cmp_states(a: &State, b: &State, goal: usize) -> Ordering {
+    a.robots[goal].cmp(&b.robots[goal])
+        .then_with(|| a.robots.iter().rev().cmp(b.robots.iter().rev()))
+}
+
+/// Geodes get cracked rather than collected, and are countable.
+fn describe_resource(name: &str) -> (&'static str, &'static str, String, &'static str) {
+    // robot kind, verb, plural, what the stockpile is called
+    if name == "geode" {
+        ("cracking", "crack", "geodes".to_string(), "open ")
+    }
+    else {
+        ("collecting", "collect", name.to_string(), "")
+    }
+}
+
+fn describe_robot(blueprint: &Blueprint, resource: usize) -> String {
+    let name = &blueprint.resources[resource];
+    format!("{}-{} robot", name, describe_resource(name).0)
+}
+
+fn describe_cost(blueprint: &Blueprint, recipe: &Recipe) -> String {
+    let parts = recipe.costs.iter().map(|(i, n)| format!("{} {}", n, blueprint.resources[*i])).collect::<Vec<_>>();
+    match parts.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => "nothing".to_string(),
+    }
+}
+
+/// Plays the plan out minute by minute from state, the way the puzzle describes it.
+pub fn describe_plan(blueprint: &Blueprint, state: &State, plan: &Plan) -> String {
+    let mut resources = state.resources;
+    let mut robots = state.robots;
+    let minutes = state.time_remaining as usize;
+
+    let mut s = String::new();
+    for (i, action) in plan.actions.iter().copied().enumerate() {
+        s += &format!("== Minute {} ==\n", minutes - plan.actions.len() + i + 1);
+
+        let recipe = match action {
+            Action::DoNothing => None,
+            Action::Build(r) => Some(&blueprint.recipes[r]),
+        };
+        if let Some(recipe) = recipe {
+            for (i, n) in &recipe.costs {
+                resources[*i] -= n;
+            }
+            let robot = describe_robot(blueprint, recipe.robot);
+            let article = if robot.starts_with(['a', 'e', 'i', 'o', 'u']) { "an" } else { "a" };
+            s += &format!("Spend {} to start building {} {}.\n", describe_cost(blueprint, recipe), article, robot);
+        }
+
+        for (i, name) in blueprint.resources.iter().enumerate() {
+            let count = robots[i];
+            if count == 0 {
+                continue;
+            }
+            resources[i] += count;
+            let (kind, verb, plural_name, adjective) = describe_resource(name);
+            let plural = count != 1;
+            s += &format!("{} {}-{} robot{} {}{} {} {}; you now have {} {}{}.\n",
+                count, name, kind, if plural { "s" } else { "" }, verb, if plural { "" } else { "s" },
+                count, if plural { &plural_name } else { name },
+                resources[i], adjective, if resources[i] == 1 { name } else { &plural_name });
+        }
+
+        if let Some(recipe) = recipe {
+            robots[recipe.robot] += 1;
+            s += &format!("The new {} is ready; you now have {} of them.\n", describe_robot(blueprint, recipe.robot), robots[recipe.robot]);
+        }
+
+        s += "\n";
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const SAMPLE: &str = "Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.";
+    const SAMPLE_2: &str = "Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian.";
+
+    #[test]
+    fn test_parse_blueprint() {
+        let blueprint = parse_blueprint(SAMPLE).unwrap();
+        assert_eq!(blueprint.id, 1);
+        assert_eq!(blueprint.resources, vec!["ore", "clay", "obsidian", "geode"]);
+        assert_eq!(blueprint.recipes, vec![
+            Recipe { robot: 0, costs: vec![(0, 4)] },
+            Recipe { robot: 1, costs: vec![(0, 2)] },
+            Recipe { robot: 2, costs: vec![(0, 3), (1, 14)] },
+            Recipe { robot: 3, costs: vec![(0, 2), (2, 7)] },
+        ]);
+
+        let blueprint = parse_blueprint("Blueprint 7: Each wood robot costs 1 wood. Each stone robot costs 2 wood, 1 sand and 3 wood. Each sand robot costs 5 wood.").unwrap();
+        assert_eq!(blueprint.resources, vec!["wood", "stone", "sand"]);
+        assert_eq!(blueprint.recipes[1], Recipe { robot: 1, costs: vec![(0, 5), (2, 1)] });
+
+        assert!(parse_blueprint("Blueprint x: Each ore robot costs 4 ore.").is_err());
+        assert!(parse_blueprint("Blueprint 1: Each ore robot costs lots of ore.").is_err());
+        assert!(parse_blueprint("Blueprint 1: Every ore robot costs 4 ore.").is_err());
+        assert!(parse_blueprint("Blueprint 1:").is_err());
+        assert!(parse_blueprint("Blueprint 1: Each a robot costs 1 b, 1 c, 1 d, 1 e, 1 f, 1 g, 1 h and 1 i.").is_err());
+    }
+
+    #[test]
+    fn test_find_max_resource_sample() {
+        let blueprint = parse_blueprint(SAMPLE).unwrap();
+        let geode = blueprint.resource_index("geode").unwrap();
+
+        let initial_state = State::new(&blueprint, 0, 24);
+        let mut stats = SearchStats::default();
+        let (geodes, plan) = find_max_resource(&initial_state, &blueprint, geode, &mut stats);
+        assert_eq!(geodes, 9);
+        assert_eq!(plan.actions.len(), 24);
+        assert!(stats.nodes_pruned > 0);
+
+        let mut state = initial_state;
+        for action in &plan.actions {
+            tick(&mut state, &blueprint, *action);
+        }
+        assert_eq!(state.resources[geode], 9);
+
+        let narrative = describe_plan(&blueprint, &initial_state, &plan);
+        assert!(narrative.starts_with("\
+== Minute 1 ==
+1 ore-collecting robot collects 1 ore; you now have 1 ore.
+
+"));
+        assert!(narrative.contains("Spend 3 ore and 14 clay to start building an obsidian-collecting robot.\n"));
+        assert!(narrative.contains("1 geode-cracking robot cracks 1 geode; you now have 1 open geode.\n"));
+        assert!(narrative.ends_with("you now have 9 open geodes.\n\n"));
+    }
+
+    #[test]
+    fn test_find_max_resource_other_shapes() {
+        // one resource, doubling up every time we can
+        let blueprint = parse_blueprint("Blueprint 1: Each coin robot costs 2 coin.").unwrap();
+        let mut stats = SearchStats::default();
+        let (coins, _) = find_max_resource(&State::new(&blueprint, 0, 6), &blueprint, 0, &mut stats);
+        // building once at minute 3 gets 1 + 1 + 1 + 2 + 2 + 2 collected, less 2 spent
+        assert_eq!(coins, 7);
+
+        // five resources, the last needing two others
+        let blueprint = parse_blueprint("Blueprint 2: \
+            Each ore robot costs 2 ore. \
+            Each clay robot costs 1 ore. \
+            Each sand robot costs 1 ore. \
+            Each glass robot costs 1 clay and 1 sand. \
+            Each gem robot costs 1 ore, 2 glass and 1 sand.").unwrap();
+        let gem = blueprint.resource_index("gem").unwrap();
+        let mut stats = SearchStats::default();
+        let (gems, plan) = find_max_resource(&State::new(&blueprint, 0, 12), &blueprint, gem, &mut stats);
+        assert!(gems > 0);
+
+        let mut state = State::new(&blueprint, 0, 12);
+        for action in &plan.actions {
+            tick(&mut state, &blueprint, *action);
+        }
+        assert_eq!(state.resources[gem], gems);
+
+        // no way of ever getting the goal
+        let blueprint = parse_blueprint("Blueprint 3: Each ore robot costs 1 ore. Each gold robot costs 1 unobtainium.").unwrap();
+        let gold = blueprint.resource_index("gold").unwrap();
+        let (gold_count, plan) = find_max_resource(&State::new(&blueprint, 0, 10), &blueprint, gold, &mut SearchStats::default());
+        assert_eq!(gold_count, 0);
+        assert_eq!(plan.actions, vec![Action::DoNothing; 10]);
+
+        // starting with a goal robot, but with the next goal robot too far off to ever build
+        let blueprint = parse_blueprint("Blueprint 4: Each ore robot costs 2 geode. Each clay robot costs 10 ore. Each geode robot costs 1 clay.").unwrap();
+        let geode = blueprint.resource_index("geode").unwrap();
+        let initial_state = State::new(&blueprint, geode, 6);
+        let (geodes, _) = find_max_resource(&initial_state, &blueprint, geode, &mut SearchStats::default());
+        assert_eq!(geodes, 6);
+        assert!(get_max_possible_resource(&initial_state, &blueprint, geode) >= geodes);
+    }
+
+    /// Tries everything we can afford every minute with no pruning at all,
+    /// remembering the most we can end up with from every state on the way.
+    fn find_max_resource_exhaustive(state: &State, blueprint: &Blueprint, goal: usize, most_from: &mut HashMap<State, i32>) -> i32 {
+        if state.time_remaining == 0 {
+            return state.resources[goal];
+        }
+        if let Some(most) = most_from.get(state) {
+            return *most;
+        }
+
+        let builds = (0..blueprint.recipes.len())
+            .filter(|r| state.can_afford(&blueprint.recipes[*r]))
+            .map(Action::Build);
+        let most = std::iter::once(Action::DoNothing).chain(builds)
+            .map(|action| {
+                let mut s = *state;
+                tick(&mut s, blueprint, action);
+                find_max_resource_exhaustive(&s, blueprint, goal, most_from)
+            })
+            .max()
+            .unwrap();
+
+        most_from.insert(*state, most);
+        most
+    }
+
+    #[test]
+    fn test_max_possible_resource_is_an_upper_bound() {
+        // blueprint, starting robot, goal, minutes, and how many minutes of the best plan to play out before looking at everything
+        let cases = [
+            (SAMPLE, "ore", "geode", 24, 15),
+            (SAMPLE_2, "ore", "geode", 24, 15),
+            (SAMPLE, "ore", "geode", 12, 0),
+            ("Blueprint 3: \
+                Each ore robot costs 2 ore. \
+                Each clay robot costs 1 ore. \
+                Each sand robot costs 1 ore. \
+                Each glass robot costs 1 clay and 1 sand. \
+                Each gem robot costs 1 ore, 2 glass and 1 sand.", "ore", "gem", 10, 0),
+            ("Blueprint 4: Each ore robot costs 2 geode. Each clay robot costs 10 ore. Each geode robot costs 1 clay.", "geode", "geode", 12, 0),
+        ];
+
+        let mut starts = cases.into_iter().map(|(line, starting_robot, goal, minutes, head_start)| {
+            let blueprint = parse_blueprint(line).unwrap();
+            let starting_robot = blueprint.resource_index(starting_robot).unwrap();
+            let goal = blueprint.resource_index(goal).unwrap();
+
+            let mut state = State::new(&blueprint, starting_robot, minutes);
+            let (_, plan) = find_max_resource(&state, &blueprint, goal, &mut SearchStats::default());
+            for action in &plan.actions[..head_start] {
+                tick(&mut state, &blueprint, *action);
+            }
+            (blueprint, goal, state)
+        }).collect::<Vec<_>>();
+
+        // enough obsidian for a geode robot without ever having had an obsidian robot
+        let blueprint = parse_blueprint(SAMPLE).unwrap();
+        let mut state = State::new(&blueprint, 0, 10);
+        state.resources[0] = 4;
+        state.resources[2] = 14;
+        starts.push((blueprint, 3, state));
+
+        for (blueprint, goal, initial_state) in &starts {
+            let mut most_from = HashMap::new();
+            let most = find_max_resource_exhaustive(initial_state, blueprint, *goal, &mut most_from);
+            assert_eq!(find_max_resource(initial_state, blueprint, *goal, &mut SearchStats::default()).0, most, "blueprint {}", blueprint.id);
+
+            // every state we could ever get to, not just the ones the search happens to look at
+            for (state, most) in &most_from {
+                let potential = get_max_possible_resource(state, blueprint, *goal);
+                assert!(potential >= *most, "blueprint {}: bound of {} but {} is possible from {:?}", blueprint.id, potential, most, state);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_max_resource_seq() {
+        {
+            let vals = get_max_resource_seq(0, 1).take(5).collect::<Vec<_>>();
+            assert_eq!(vals, vec![0, 1, 3, 6, 10]);
+        }
+        {
+            let vals = get_max_resource_seq(5, 1).take(5).collect::<Vec<_>>();
+            assert_eq!(vals, vec![5, 6, 8, 11, 15]);
+        }
+        {
+            let vals = get_max_resource_seq(5, 0).take(5).collect::<Vec<_>>();
+            assert_eq!(vals, vec![5, 5, 6, 8, 11]);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod search;
+pub mod raster;
+pub mod cycle;
+pub mod droplet;
+pub mod economy;
@@ -0,0 +1,85 @@
+use std::fmt;
+
+/// A segment of a polyline that can't be drawn on a grid.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RasterizeError {
+    /// Index of the segment within the polyline, i.e. of its first point.
+    pub segment: usize,
+    pub from: (i64, i64),
+    pub to: (i64, i64),
+}
+
+impl fmt::Display for RasterizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "segment {},{} -> {},{} is not horizontal, vertical or 45 degrees", self.from.0, self.from.1, self.to.0, self.to.1)
+    }
+}
+
+/// Turns a polyline into the grid cells it passes through.
+/// Segments have to be horizontal or vertical, or 45 degree diagonals if allowed.
+pub trait Rasterize<P> {
+    /// Cells in order along the line. Corners where segments join only appear once.
+    fn rasterize(&self, allow_diagonals: bool) -> Result<Vec<P>, RasterizeError>;
+}
+
+impl<P> Rasterize<P> for [P]
+where
+    P: Copy + Into<(i64, i64)> + From<(i64, i64)>,
+{
+    fn rasterize(&self, allow_diagonals: bool) -> Result<Vec<P>, RasterizeError> {
+        let mut cells = Vec::new();
+        if let Some(first) = self.first() {
+            cells.push(*first);
+        }
+
+        for (i, window) in self.windows(2).enumerate() {
+            let from: (i64, i64) = window[0].into();
+            let to: (i64, i64) = window[1].into();
+            let dx = to.0 - from.0;
+            let dy = to.1 - from.1;
+
+            let is_straight = dx == 0 || dy == 0;
+            let is_diagonal = dx.abs() == dy.abs();
+            if !(is_straight || (allow_diagonals && is_diagonal)) {
+                return Err(RasterizeError { segment: i, from, to });
+            }
+
+            let steps = dx.abs().max(dy.abs());
+            for step in 1..=steps {
+                cells.push(P::from((from.0 + (dx.signum() * step), from.1 + (dy.signum() * step))));
+            }
+        }
+
+        Ok(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rasterize_straight() {
+        let line = [(498, 4), (498, 6), (496, 6)];
+        assert_eq!(line.rasterize(false), Ok(vec![(498, 4), (498, 5), (498, 6), (497, 6), (496, 6)]));
+    }
+
+    #[test]
+    fn test_rasterize_single_point() {
+        assert_eq!([(3, 3)].rasterize(false), Ok(vec![(3, 3)]));
+        assert_eq!(([] as [(i64, i64); 0]).rasterize(false), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_rasterize_diagonal() {
+        let line = [(0, 0), (2, 2), (4, 0)];
+        assert_eq!(line.rasterize(true), Ok(vec![(0, 0), (1, 1), (2, 2), (3, 1), (4, 0)]));
+        assert_eq!(line.rasterize(false), Err(RasterizeError { segment: 0, from: (0, 0), to: (2, 2) }));
+    }
+
+    #[test]
+    fn test_rasterize_bad_angle() {
+        let line = [(0, 0), (0, 2), (3, 3)];
+        assert_eq!(line.rasterize(true), Err(RasterizeError { segment: 1, from: (0, 2), to: (3, 3) }));
+    }
+}
@@ -0,0 +1,232 @@
+use std::{collections::{HashMap, VecDeque, BinaryHeap, hash_map::Entry}, hash::Hash, cmp::Ordering, ops::Add};
+
+/// Everything a search found out about the nodes it reached.
+#[derive(Debug)]
+pub struct SearchResult<N, C> {
+    /// Cost of the cheapest known route from a start node.
+    pub distances: HashMap<N, C>,
+    /// The node each reached node was reached from. Start nodes have no entry.
+    pub predecessors: HashMap<N, N>,
+    /// The first goal node reached, if the search stopped at one.
+    pub goal: Option<N>,
+}
+
+impl<N: Hash + Eq + Clone, C> SearchResult<N, C> {
+    /// Walks the predecessor map back from node, giving the route from its start node to node inclusive.
+    pub fn path_to(&self, node: &N) -> Option<Vec<N>> {
+        if !self.distances.contains_key(node) {
+            return None;
+        }
+
+        let mut path = vec![node.clone()];
+        let mut current = node;
+        while let Some(prev) = self.predecessors.get(current) {
+            path.push(prev.clone());
+            current = prev;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Route to the goal the search stopped at.
+    pub fn goal_path(&self) -> Option<Vec<N>> {
+        self.goal.as_ref().and_then(|g| self.path_to(g))
+    }
+
+    pub fn goal_distance(&self) -> Option<&C> {
+        self.goal.as_ref().map(|g| &self.distances[g])
+    }
+}
+
+/// Breadth-first search where every edge costs 1.
+/// Stops at the first node satisfying is_goal, otherwise visits everything reachable.
+pub fn bfs<N, S, I, G>(starts: impl IntoIterator<Item=N>, mut successors: S, mut is_goal: G) -> SearchResult<N, usize>
+where
+    N: Hash + Eq + Clone,
+    S: FnMut(&N) -> I,
+    I: IntoIterator<Item=N>,
+    G: FnMut(&N) -> bool,
+{
+    let mut distances = HashMap::new();
+    let mut predecessors = HashMap::new();
+    let mut open_list = VecDeque::new();
+
+    for start in starts {
+        if let Entry::Vacant(e) = distances.entry(start.clone()) {
+            e.insert(0);
+            open_list.push_back(start);
+        }
+    }
+
+    while let Some(node) = open_list.pop_front() {
+        if is_goal(&node) {
+            return SearchResult { distances, predecessors, goal: Some(node) };
+        }
+
+        let cost = distances[&node];
+        for succ in successors(&node) {
+            if let Entry::Vacant(e) = distances.entry(succ.clone()) {
+                e.insert(cost + 1);
+                predecessors.insert(succ.clone(), node.clone());
+                open_list.push_back(succ);
+            }
+        }
+    }
+
+    SearchResult { distances, predecessors, goal: None }
+}
+
+/// Dijkstra's algorithm over non-negative edge costs. C::default() is taken to be zero.
+pub fn dijkstra<N, C, S, I, G>(starts: impl IntoIterator<Item=N>, successors: S, is_goal: G) -> SearchResult<N, C>
+where
+    N: Hash + Eq + Clone,
+    C: Copy + Ord + Add<Output=C> + Default,
+    S: FnMut(&N) -> I,
+    I: IntoIterator<Item=(N, C)>,
+    G: FnMut(&N) -> bool,
+{
+    astar(starts, successors, |_| C::default(), is_goal)
+}
+
+/// A* search. The heuristic must never overestimate the remaining cost
+/// or the route found may not be the cheapest.
+pub fn astar<N, C, S, I, H, G>(starts: impl IntoIterator<Item=N>, mut successors: S, mut heuristic: H, mut is_goal: G) -> SearchResult<N, C>
+where
+    N: Hash + Eq + Clone,
+    C: Copy + Ord + Add<Output=C> + Default,
+    S: FnMut(&N) -> I,
+    I: IntoIterator<Item=(N, C)>,
+    H: FnMut(&N) -> C,
+    G: FnMut(&N) -> bool,
+{
+    let mut distances = HashMap::<N, C>::new();
+    let mut predecessors = HashMap::new();
+    let mut open_list = BinaryHeap::new();
+
+    for start in starts {
+        if let Entry::Vacant(e) = distances.entry(start.clone()) {
+            e.insert(C::default());
+            open_list.push(HeapEntry { priority: heuristic(&start), cost: C::default(), node: start });
+        }
+    }
+
+    while let Some(HeapEntry { cost, node, .. }) = open_list.pop() {
+        // stale entry, we already found a cheaper way here
+        if cost > distances[&node] {
+            continue;
+        }
+
+        if is_goal(&node) {
+            return SearchResult { distances, predecessors, goal: Some(node) };
+        }
+
+        for (succ, edge_cost) in successors(&node) {
+            let succ_cost = cost + edge_cost;
+            match distances.entry(succ.clone()) {
+                Entry::Occupied(e) if *e.get() <= succ_cost => { continue; }
+                Entry::Occupied(mut e) => { e.insert(succ_cost); }
+                Entry::Vacant(e) => { e.insert(succ_cost); }
+            }
+            predecessors.insert(succ.clone(), node.clone());
+            open_list.push(HeapEntry { priority: succ_cost + heuristic(&succ), cost: succ_cost, node: succ });
+        }
+    }
+
+    SearchResult { distances, predecessors, goal: None }
+}
+
+struct HeapEntry<N, C> {
+    priority: C,
+    cost: C,
+    node: N,
+}
+
+// Ordered only by priority, reversed so that BinaryHeap pops the cheapest first.
+impl<N, C: Ord> Ord for HeapEntry<N, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+impl<N, C: Ord> PartialOrd for HeapEntry<N, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N, C: Ord> PartialEq for HeapEntry<N, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<N, C: Ord> Eq for HeapEntry<N, C> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Cell = (i32, i32);
+
+    fn grid_successors<'a>(walls: &'a [&'a str]) -> impl FnMut(&Cell) -> Vec<(Cell, i32)> + 'a {
+        move |&(x, y)| {
+            [(x+1, y), (x-1, y), (x, y+1), (x, y-1)].into_iter()
+                .filter(|&(x, y)| y >= 0 && x >= 0 && (y as usize) < walls.len() && (x as usize) < walls[y as usize].len())
+                .filter_map(|(x, y)| match walls[y as usize].as_bytes()[x as usize] {
+                    b'#' => None,
+                    c => Some(((x, y), i32::from(c - b'0'))),
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_bfs_path() {
+        let result = bfs([0], |&n: &i32| [n + 1, n * 2], |&n| n == 10);
+        assert_eq!(result.goal, Some(10));
+        assert_eq!(result.goal_distance(), Some(&5));
+        assert_eq!(result.goal_path(), Some(vec![0, 1, 2, 4, 5, 10]));
+    }
+
+    #[test]
+    fn test_bfs_exhaustive() {
+        let result = bfs([0, 10], |&n: &i32| [n - 1, n + 1].into_iter().filter(|x| (0..=10).contains(x)), |_| false);
+        assert_eq!(result.goal, None);
+        assert_eq!(result.distances.len(), 11);
+        assert_eq!(result.distances[&5], 5);
+        assert_eq!(result.distances[&7], 3);
+        assert_eq!(result.path_to(&8), Some(vec![10, 9, 8]));
+        assert_eq!(result.path_to(&11), None);
+    }
+
+    #[test]
+    fn test_dijkstra_and_astar_agree() {
+        let walls = [
+            "11111",
+            "1###1",
+            "19191",
+            "1#1#1",
+            "11111",
+        ];
+        let goal = (2, 2);
+
+        let d = dijkstra([(0, 0)], grid_successors(&walls), |&n| n == goal);
+        let a = astar([(0, 0)], grid_successors(&walls), |&(x, y)| (x - goal.0).abs() + (y - goal.1).abs(), |&n| n == goal);
+
+        assert_eq!(d.goal_distance(), Some(&8));
+        assert_eq!(a.goal_distance(), Some(&8));
+        assert_eq!(a.goal_path().unwrap().len(), 9);
+    }
+
+    #[test]
+    fn test_dijkstra_prefers_cheaper_longer_route() {
+        let result = dijkstra([0], |&n: &i32| match n {
+            0 => vec![(1, 10), (2, 1)],
+            2 => vec![(3, 1)],
+            3 => vec![(1, 1)],
+            _ => vec![],
+        }, |_| false);
+        assert_eq!(result.distances[&1], 3);
+        assert_eq!(result.path_to(&1), Some(vec![0, 2, 3, 1]));
+    }
+}